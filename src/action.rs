@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+
+use crate::save::BattleStart;
+use crate::state::GameState;
+use crate::turn::{Phase, PhaseChanged, TurnStarted};
+use crate::unit::moving;
+
+mod events;
+mod resource;
+mod systems;
+
+pub use events::*;
+pub use resource::*;
+
+const UNDO_KEY: KeyCode = KeyCode::Back;
+
+/// Where actions are applied.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ExecuteActions;
+
+/// Where input and the AI send actions, all before [`ExecuteActions`] so that nothing sees a
+/// click again once an action taken on it has changed the battle.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct IssueActions;
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionLog>()
+            .init_resource::<MoveHistory>()
+            .add_event::<Action>()
+            .configure_sets(Update, IssueActions.before(ExecuteActions))
+            .add_systems(OnExit(GameState::Battle), systems::clear_history)
+            .add_systems(
+                Update,
+                (
+                    systems::feed_replay.run_if(
+                        resource_exists::<Replaying>()
+                            .and_then(resource_exists::<BattleStart>())
+                            .and_then(not(moving())),
+                    ),
+                    systems::undo_input.in_set(IssueActions).run_if(
+                        in_state(Phase::Player)
+                            .and_then(not(resource_exists::<Replaying>()))
+                            .and_then(not(moving())),
+                    ),
+                    systems::clear_history
+                        .run_if(on_event::<TurnStarted>().or_else(on_event::<PhaseChanged>())),
+                    systems::execute_actions.in_set(ExecuteActions),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Battle)),
+            );
+    }
+}
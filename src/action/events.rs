@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::map::Coordinates;
+
+/// Everything a player, or the AI, can do in battle. Units are referred to by the tile they
+/// stand on so actions stay valid across runs.
+#[derive(Event, Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Action {
+    /// Selects the unit on a tile, or clears the selection.
+    Select(Option<Coordinates>),
+    /// Walks the selected unit along a path that excludes its own tile.
+    Move(Vec<Coordinates>),
+    /// Attacks the unit on a tile with the selected unit.
+    Attack(Coordinates),
+    /// Takes back the last move made since an attack or the end of a turn.
+    Undo,
+    EndTurn,
+}
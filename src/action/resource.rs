@@ -0,0 +1,40 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::map::Position;
+use crate::turn::{Actions, Phase};
+
+use super::Action;
+
+/// An applied [`Action`] and the turn it was applied on.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActionRecord {
+    pub turn: u32,
+    pub phase: Phase,
+    pub action: Action,
+}
+
+/// Every action applied since the battle started.
+#[derive(Resource, Default, Debug)]
+pub struct ActionLog(pub Vec<ActionRecord>);
+
+/// Recorded actions still to be fed back in, each once its turn comes up. Input is ignored
+/// while replaying.
+#[derive(Resource, Default, Debug)]
+pub struct Replaying(pub VecDeque<ActionRecord>);
+
+/// Where a unit stood before a move, restored by [`Action::Undo`].
+#[derive(Debug)]
+pub struct MoveRecord {
+    pub entity: Entity,
+    pub position: Position,
+    pub flip_x: bool,
+    pub actions: Actions,
+}
+
+/// Moves that can still be taken back, most recent last. Cleared once an action is committed
+/// or the turn ends.
+#[derive(Resource, Default, Debug)]
+pub struct MoveHistory(pub Vec<MoveRecord>);
@@ -0,0 +1,706 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::combat::{roll_attack, Attacked, Defeated, Weapon};
+use crate::fog::{FogOfWar, Sight};
+use crate::map::{Coordinates, Floor, Map, Position, Terrain};
+use crate::rng::{Rng, Stream};
+use crate::turn::{Actions, EndTurn, Phase, TurnNumber};
+use crate::unit::{Faction, Mode, Modifiers, Movement, SelectedUnit, Selection, Stats, Unit};
+
+use super::{Action, ActionLog, ActionRecord, MoveHistory, MoveRecord, Replaying, UNDO_KEY};
+
+type UnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Position,
+        &'static Faction,
+        &'static mut Stats,
+        &'static Modifiers,
+        &'static Weapon,
+        &'static mut Actions,
+        &'static mut Transform,
+        &'static mut TextureAtlasSprite,
+    ),
+    With<Unit>,
+>;
+
+pub fn undo_input(keys: Res<Input<KeyCode>>, mut actions: EventWriter<Action>) {
+    if keys.just_pressed(UNDO_KEY) {
+        actions.send(Action::Undo);
+    }
+}
+
+pub fn clear_history(mut history: ResMut<MoveHistory>) {
+    history.0.clear();
+}
+
+pub fn feed_replay(
+    mut commands: Commands,
+    turn_number: Res<TurnNumber>,
+    phase: Res<State<Phase>>,
+    mut replaying: ResMut<Replaying>,
+    mut actions: EventWriter<Action>,
+) {
+    let Some(record) = replaying.0.front() else {
+        info!("replay finished");
+        commands.remove_resource::<Replaying>();
+        return;
+    };
+
+    if record.turn != turn_number.0 || record.phase != *phase.get() {
+        return;
+    }
+
+    if let Some(record) = replaying.0.pop_front() {
+        actions.send(record.action);
+    }
+}
+
+/// Why an [`Action`] was refused.
+#[derive(Debug, Error)]
+enum Rejection {
+    #[error("no unit is selected")]
+    NoSelection,
+    #[error("the selected unit is gone")]
+    SelectedUnitGone,
+    #[error("a unit is on the move")]
+    Moving,
+    #[error("no unit stands there")]
+    NoUnit,
+    #[error("the unit can't be seen")]
+    Hidden,
+    #[error("the unit isn't one of the acting faction")]
+    NotOwnUnit,
+    #[error("the unit has already moved")]
+    AlreadyMoved,
+    #[error("the path can't be walked")]
+    BadPath,
+    #[error("the unit has already acted")]
+    AlreadyActed,
+    #[error("the target is an ally")]
+    Ally,
+    #[error("the target is out of reach")]
+    OutOfReach,
+    #[error("there is no move to take back")]
+    NothingToUndo,
+}
+
+/// Applies every [`Action`], whoever issued it, and records the ones that were allowed.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_actions(
+    mut map: ResMut<Map>,
+    fog: Res<FogOfWar>,
+    phase: Res<State<Phase>>,
+    mut rng: ResMut<Rng>,
+    turn_number: Res<TurnNumber>,
+    mut selection: ResMut<Selection>,
+    mut log: ResMut<ActionLog>,
+    mut history: ResMut<MoveHistory>,
+    mut actions: EventReader<Action>,
+    mut end_turn: EventWriter<EndTurn>,
+    mut attacked: EventWriter<Attacked>,
+    mut defeated: EventWriter<Defeated>,
+    mut unit_query: UnitQuery,
+) {
+    for action in actions.read() {
+        let number = turn_number.0;
+        let faction = phase.get().faction();
+
+        if let Err(rejection) = validate(
+            action,
+            faction,
+            &selection,
+            &map,
+            &fog,
+            &history,
+            &unit_query,
+        ) {
+            warn!("rejected {:?}: {}", action, rejection);
+            continue;
+        }
+
+        match action {
+            Action::Select(coordinates) => {
+                selection.0 = coordinates
+                    .and_then(|coordinates| map.occupants.get(&coordinates))
+                    .map(|entity| SelectedUnit {
+                        entity: *entity,
+                        movement: None,
+                        mode: Mode::Move,
+                    });
+            }
+            Action::Move(path) => {
+                let Some(selected_unit) = &mut selection.0 else {
+                    continue;
+                };
+
+                let Ok((position, .., unit_actions, _, sprite)) =
+                    unit_query.get(selected_unit.entity)
+                else {
+                    continue;
+                };
+
+                history.0.push(MoveRecord {
+                    entity: selected_unit.entity,
+                    position: *position,
+                    flip_x: sprite.flip_x,
+                    actions: *unit_actions,
+                });
+
+                let path: VecDeque<Position> = path
+                    .iter()
+                    .map(|coordinates| Position {
+                        coordinates: *coordinates,
+                        floor: map.surface(*coordinates).unwrap_or_default(),
+                        order: position.order,
+                    })
+                    .collect();
+                selected_unit.movement = Some(Movement::new(path));
+            }
+            Action::Attack(target) => {
+                let Some(selected_unit) = &mut selection.0 else {
+                    continue;
+                };
+
+                let attacker = selected_unit.entity;
+                let Some((target_entity, target_faction, dealt, hp)) =
+                    attack(&map, &mut rng, attacker, *target, &mut unit_query)
+                else {
+                    continue;
+                };
+
+                debug!(
+                    "attack from {:?} on {:?}: {:?}",
+                    attacker, target_entity, dealt
+                );
+
+                attacked.send(Attacked {
+                    attacker,
+                    target: target_entity,
+                    damage: dealt,
+                });
+
+                if hp == 0 {
+                    defeated.send(Defeated {
+                        entity: target_entity,
+                        faction: target_faction,
+                    });
+                }
+
+                selected_unit.mode = Mode::Move;
+                history.0.clear();
+            }
+            Action::Undo => {
+                let Some(record) = history.0.pop() else {
+                    continue;
+                };
+
+                undo(&mut map, &mut unit_query, &record);
+                selection.0 = Some(SelectedUnit {
+                    entity: record.entity,
+                    movement: None,
+                    mode: Mode::Move,
+                });
+            }
+            Action::EndTurn => {
+                history.0.clear();
+                end_turn.send(EndTurn);
+            }
+        }
+
+        log.0.push(ActionRecord {
+            turn: number,
+            phase: *phase.get(),
+            action: action.clone(),
+        });
+    }
+}
+
+/// Checks `action` against the rules of the battle for the acting `faction`. Every action
+/// goes through here, whether it was clicked, planned by the AI or replayed.
+fn validate(
+    action: &Action,
+    faction: Faction,
+    selection: &Selection,
+    map: &Map,
+    fog: &FogOfWar,
+    history: &MoveHistory,
+    unit_query: &UnitQuery,
+) -> Result<(), Rejection> {
+    let moving = selection
+        .0
+        .as_ref()
+        .is_some_and(|selected_unit| selected_unit.movement.is_some());
+    if moving && *action != Action::EndTurn {
+        return Err(Rejection::Moving);
+    }
+
+    // Units of other factions are only there for whoever can see them.
+    let seen = |coordinates: &Coordinates| -> Result<Entity, Rejection> {
+        let entity = *map.occupants.get(coordinates).ok_or(Rejection::NoUnit)?;
+        let (_, unit_faction, ..) = unit_query.get(entity).map_err(|_| Rejection::NoUnit)?;
+
+        if *unit_faction != faction && fog.sight(faction, *coordinates) != Sight::Visible {
+            return Err(Rejection::Hidden);
+        }
+        Ok(entity)
+    };
+
+    let selected = || {
+        let selected_unit = selection.0.as_ref().ok_or(Rejection::NoSelection)?;
+        unit_query
+            .get(selected_unit.entity)
+            .map_err(|_| Rejection::SelectedUnitGone)
+    };
+
+    match action {
+        Action::Select(None) | Action::EndTurn => Ok(()),
+        Action::Select(Some(coordinates)) => seen(coordinates).map(|_| ()),
+        Action::Move(path) => {
+            let (position, unit_faction, stats, modifiers, _, unit_actions, ..) = selected()?;
+
+            if *unit_faction != faction {
+                return Err(Rejection::NotOwnUnit);
+            }
+            if !unit_actions.can_move() {
+                return Err(Rejection::AlreadyMoved);
+            }
+
+            // Planned around the units the faction knows of, like its paths are. A hidden unit
+            // in the way stops the move when it's reached.
+            let occupants = fog.known_occupants(map, faction, |entity| {
+                unit_query.get(entity).ok().map(|(_, faction, ..)| *faction)
+            });
+            let stats = stats.with(modifiers);
+            let terrain = Terrain {
+                map,
+                jump: Floor(stats.jump),
+                occupants: &occupants,
+            };
+
+            let walkable = path
+                .iter()
+                .scan(position.coordinates, |from, to| {
+                    let step = terrain.can_step(*from, *to);
+                    *from = *to;
+                    Some(step)
+                })
+                .all(|step| step);
+
+            if path.is_empty() || path.len() as i32 > stats.move_range || !walkable {
+                return Err(Rejection::BadPath);
+            }
+            Ok(())
+        }
+        Action::Attack(target) => {
+            let (position, unit_faction, _, _, weapon, unit_actions, ..) = selected()?;
+
+            if *unit_faction != faction {
+                return Err(Rejection::NotOwnUnit);
+            }
+            if !unit_actions.can_act() {
+                return Err(Rejection::AlreadyActed);
+            }
+
+            let target_entity = seen(target)?;
+            let (target_position, target_faction, ..) = unit_query
+                .get(target_entity)
+                .map_err(|_| Rejection::NoUnit)?;
+
+            if target_faction == unit_faction {
+                return Err(Rejection::Ally);
+            }
+            if !weapon.reaches(position.coordinates, target_position.coordinates)
+                || !weapon.has_clear_shot(map, position, target_position.coordinates)
+            {
+                return Err(Rejection::OutOfReach);
+            }
+            Ok(())
+        }
+        Action::Undo => match history.0.last() {
+            Some(_) => Ok(()),
+            None => Err(Rejection::NothingToUndo),
+        },
+    }
+}
+
+/// Resolves an attack [`validate`] allowed, returning the target, its faction, the damage
+/// dealt and the target's HP afterwards.
+fn attack(
+    map: &Map,
+    rng: &mut Rng,
+    attacker: Entity,
+    target: Coordinates,
+    unit_query: &mut UnitQuery,
+) -> Option<(Entity, Faction, Option<i32>, i32)> {
+    let target_entity = *map.occupants.get(&target)?;
+
+    let (attacker_position, _, attacker_stats, attacker_modifiers, weapon, ..) =
+        unit_query.get(attacker).ok()?;
+
+    let attacker_position = *attacker_position;
+    let attacker_stats = attacker_stats.with(attacker_modifiers);
+    let weapon = *weapon;
+
+    let (target_position, target_faction, mut target_stats, target_modifiers, ..) =
+        unit_query.get_mut(target_entity).ok()?;
+
+    let dealt = roll_attack(
+        rng.stream(Stream::Combat),
+        &attacker_stats,
+        &weapon,
+        &target_stats.with(target_modifiers),
+        attacker_position.floor,
+        target_position.floor,
+    );
+
+    if let Some(dealt) = dealt {
+        target_stats.hp = (target_stats.hp - dealt).max(0);
+    }
+
+    Some((target_entity, *target_faction, dealt, target_stats.hp))
+}
+
+/// Puts a unit back where it stood before the move in `record`, drawn for the current
+/// orientation of the map.
+fn undo(map: &mut Map, unit_query: &mut UnitQuery, record: &MoveRecord) {
+    let Ok((mut position, .., mut actions, mut transform, mut sprite)) =
+        unit_query.get_mut(record.entity)
+    else {
+        return;
+    };
+
+    map.occupants.remove(&position.coordinates);
+    map.occupants
+        .insert(record.position.coordinates, record.entity);
+
+    *position = record.position;
+    *actions = record.actions;
+    transform.translation = map.position_to_translation(&record.position);
+    sprite.flip_x = record.flip_x;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use crate::map::Side;
+    use crate::unit::UnitBundle;
+
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Coordinates {
+        Coordinates(x, y, Side::Center)
+    }
+
+    fn spawn(world: &mut World, faction: Faction, coordinates: Coordinates) -> Entity {
+        let position = Position {
+            coordinates,
+            floor: Floor(0),
+            ..default()
+        };
+        let stats = Stats {
+            max_hp: 20,
+            hp: 20,
+            move_range: 3,
+            jump: 1,
+            attack: 6,
+            evasion: 30,
+            ..default()
+        };
+
+        let entity = world
+            .spawn(UnitBundle {
+                faction,
+                stats,
+                position,
+                ..default()
+            })
+            .id();
+        world
+            .resource_mut::<Map>()
+            .occupants
+            .insert(coordinates, entity);
+        entity
+    }
+
+    /// A knight at (0, 0) facing a raider at (3, 0) on flat ground, both in plain sight.
+    fn battle(seed: u64) -> (World, Entity, Entity) {
+        let map = Map::from_heights(&[&[0, 0, 0, 0], &[0, 0, 0, 0]]);
+        let cells = (0..4).flat_map(|x| [at(x, 0), at(x, 1)]).collect();
+        let mut fog = FogOfWar::default();
+        fog.reveal(Faction::Player, &cells);
+        fog.reveal(Faction::Enemy, &cells);
+
+        let mut world = World::new();
+        world.insert_resource(map);
+        world.insert_resource(fog);
+        world.insert_resource(State::new(Phase::Player));
+        world.insert_resource(Rng::new(seed));
+        world.insert_resource(TurnNumber(1));
+        world.init_resource::<Selection>();
+        world.init_resource::<ActionLog>();
+        world.init_resource::<MoveHistory>();
+        world.init_resource::<Events<Action>>();
+        world.init_resource::<Events<EndTurn>>();
+        world.init_resource::<Events<Attacked>>();
+        world.init_resource::<Events<Defeated>>();
+
+        let knight = spawn(&mut world, Faction::Player, at(0, 0));
+        let raider = spawn(&mut world, Faction::Enemy, at(3, 0));
+        (world, knight, raider)
+    }
+
+    fn select(world: &mut World, entity: Entity) {
+        world.resource_mut::<Selection>().0 = Some(SelectedUnit {
+            entity,
+            movement: None,
+            mode: Mode::Move,
+        });
+    }
+
+    /// Stands in for the unit's movement and action tracking, walking the selected unit to the
+    /// end of its path at once.
+    fn arrive(world: &mut World) {
+        let mut selection = world.resource_mut::<Selection>();
+        let Some(selected_unit) = selection.0.as_mut() else {
+            return;
+        };
+        let entity = selected_unit.entity;
+        let Some(to) = selected_unit
+            .movement
+            .take()
+            .and_then(|movement| movement.path.back().copied())
+        else {
+            return;
+        };
+
+        let mut unit = world.entity_mut(entity);
+        let from = *unit.get::<Position>().unwrap();
+        *unit.get_mut::<Position>().unwrap() = to;
+        unit.get_mut::<Actions>().unwrap().moved = true;
+        unit.get_mut::<TextureAtlasSprite>().unwrap().flip_x =
+            to.coordinates.0 < from.coordinates.0;
+
+        let mut map = world.resource_mut::<Map>();
+        map.occupants.remove(&from.coordinates);
+        map.occupants.insert(to.coordinates, entity);
+    }
+
+    fn execute(world: &mut World, actions: impl IntoIterator<Item = Action>) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(execute_actions);
+
+        for action in actions {
+            world.send_event(action);
+            schedule.run(world);
+            arrive(world);
+        }
+    }
+
+    fn check(world: &mut World, action: Action, faction: Faction) -> Result<(), Rejection> {
+        let map = world.remove_resource::<Map>().unwrap();
+        let fog = world.remove_resource::<FogOfWar>().unwrap();
+        let history = world.remove_resource::<MoveHistory>().unwrap();
+        let selection = world.remove_resource::<Selection>().unwrap();
+
+        let mut state = SystemState::<UnitQuery>::new(world);
+        let unit_query = state.get_mut(world);
+        let result = validate(
+            &action,
+            faction,
+            &selection,
+            &map,
+            &fog,
+            &history,
+            &unit_query,
+        );
+
+        world.insert_resource(map);
+        world.insert_resource(fog);
+        world.insert_resource(history);
+        world.insert_resource(selection);
+        result
+    }
+
+    fn units(world: &mut World) -> Vec<(Coordinates, i32)> {
+        let mut units: Vec<_> = world
+            .query_filtered::<(&Position, &Stats), With<Unit>>()
+            .iter(world)
+            .map(|(position, stats)| (position.coordinates, stats.hp))
+            .collect();
+        units.sort_by_key(|(coordinates, _)| (coordinates.0, coordinates.1));
+        units
+    }
+
+    #[test]
+    fn replaying_the_log_plays_the_battle_out_again() {
+        let (mut world, ..) = battle(5);
+        execute(
+            &mut world,
+            [
+                Action::Select(Some(at(0, 0))),
+                Action::Move(vec![at(1, 0), at(2, 0)]),
+                Action::Attack(at(3, 0)),
+                Action::Attack(at(3, 0)),
+                Action::Attack(at(3, 0)),
+                Action::EndTurn,
+            ],
+        );
+        let log = world.resource::<ActionLog>().0.clone();
+        assert_eq!(log.len(), 6);
+
+        let (mut replay, ..) = battle(5);
+        replay.insert_resource(Replaying(log.iter().cloned().collect()));
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (feed_replay, execute_actions)
+                .chain()
+                .run_if(resource_exists::<Replaying>()),
+        );
+        for _ in 0..=log.len() {
+            schedule.run(&mut replay);
+            arrive(&mut replay);
+        }
+
+        assert!(!replay.contains_resource::<Replaying>());
+        assert_eq!(replay.resource::<ActionLog>().0.len(), log.len());
+        assert_eq!(units(&mut replay), units(&mut world));
+        let units = units(&mut world);
+        assert_eq!(units[0].0, at(2, 0));
+        assert!(units[1].1 < 20, "the raider was hit");
+    }
+
+    #[test]
+    fn validate_refuses_units_of_another_faction() {
+        let (mut world, _, raider) = battle(1);
+        select(&mut world, raider);
+
+        let result = check(&mut world, Action::Move(vec![at(2, 0)]), Faction::Player);
+        assert!(matches!(result, Err(Rejection::NotOwnUnit)));
+    }
+
+    #[test]
+    fn validate_refuses_units_out_of_their_phase() {
+        let (mut world, knight, _) = battle(1);
+        select(&mut world, knight);
+
+        let action = Action::Move(vec![at(1, 0)]);
+        assert!(check(&mut world, action.clone(), Phase::Player.faction()).is_ok());
+        let result = check(&mut world, action, Phase::Enemy.faction());
+        assert!(matches!(result, Err(Rejection::NotOwnUnit)));
+    }
+
+    #[test]
+    fn validate_refuses_hidden_targets() {
+        let (mut world, knight, _) = battle(1);
+        execute(
+            &mut world,
+            [
+                Action::Select(Some(at(0, 0))),
+                Action::Move(vec![at(1, 0), at(2, 0)]),
+            ],
+        );
+        select(&mut world, knight);
+        world.insert_resource(FogOfWar::default());
+
+        let result = check(&mut world, Action::Attack(at(3, 0)), Faction::Player);
+        assert!(matches!(result, Err(Rejection::Hidden)));
+    }
+
+    #[test]
+    fn validate_refuses_targets_out_of_reach() {
+        let (mut world, knight, _) = battle(1);
+        select(&mut world, knight);
+
+        let result = check(&mut world, Action::Attack(at(3, 0)), Faction::Player);
+        assert!(matches!(result, Err(Rejection::OutOfReach)));
+    }
+
+    #[test]
+    fn undoing_every_move_puts_the_units_back() {
+        let (mut world, knight, _) = battle(1);
+        let squire = spawn(&mut world, Faction::Player, at(0, 1));
+        world.get_mut::<TextureAtlasSprite>(knight).unwrap().flip_x = true;
+
+        execute(
+            &mut world,
+            [
+                Action::Select(Some(at(0, 0))),
+                Action::Move(vec![at(1, 0)]),
+                Action::Select(Some(at(0, 1))),
+                Action::Move(vec![at(1, 1), at(2, 1)]),
+            ],
+        );
+        assert_eq!(world.get::<Position>(knight).unwrap().coordinates, at(1, 0));
+        assert!(!world.get::<TextureAtlasSprite>(knight).unwrap().flip_x);
+
+        execute(&mut world, [Action::Undo, Action::Undo]);
+
+        for (entity, coordinates) in [(knight, at(0, 0)), (squire, at(0, 1))] {
+            assert_eq!(
+                world.get::<Position>(entity).unwrap().coordinates,
+                coordinates
+            );
+            assert!(!world.get::<Actions>(entity).unwrap().moved);
+            assert_eq!(
+                world.resource::<Map>().occupants.get(&coordinates),
+                Some(&entity)
+            );
+        }
+        assert!(world.get::<TextureAtlasSprite>(knight).unwrap().flip_x);
+        assert_eq!(
+            world
+                .resource::<Selection>()
+                .0
+                .as_ref()
+                .map(|unit| unit.entity),
+            Some(knight)
+        );
+        assert!(world.resource::<MoveHistory>().0.is_empty());
+    }
+
+    #[test]
+    fn undo_is_refused_after_an_attack() {
+        let (mut world, knight, _) = battle(1);
+        execute(
+            &mut world,
+            [
+                Action::Select(Some(at(0, 0))),
+                Action::Move(vec![at(1, 0), at(2, 0)]),
+                Action::Attack(at(3, 0)),
+                Action::Undo,
+            ],
+        );
+
+        assert_eq!(world.get::<Position>(knight).unwrap().coordinates, at(2, 0));
+        assert!(matches!(
+            check(&mut world, Action::Undo, Faction::Player),
+            Err(Rejection::NothingToUndo)
+        ));
+    }
+
+    #[test]
+    fn undo_is_refused_after_ending_the_turn() {
+        let (mut world, knight, _) = battle(1);
+        execute(
+            &mut world,
+            [
+                Action::Select(Some(at(0, 0))),
+                Action::Move(vec![at(1, 0)]),
+                Action::EndTurn,
+                Action::Undo,
+            ],
+        );
+
+        assert_eq!(world.get::<Position>(knight).unwrap().coordinates, at(1, 0));
+        assert!(matches!(
+            check(&mut world, Action::Undo, Faction::Player),
+            Err(Rejection::NothingToUndo)
+        ));
+    }
+}
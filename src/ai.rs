@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+
+use crate::action::{IssueActions, Replaying};
+use crate::state::GameState;
+use crate::turn::{PhaseChanged, TurnStarted};
+use crate::unit::moving;
+
+mod plan;
+mod resource;
+mod systems;
+
+pub use plan::*;
+pub use resource::*;
+
+/// Score per point of damage a unit expects to deal from a destination.
+const DEALT_WEIGHT: f32 = 1.0;
+
+/// Score for a likely kill, scaled by the chance to hit.
+const KILL_BONUS: f32 = 10.0;
+
+/// Score lost per point of damage the opponents could deal back next turn.
+const TAKEN_WEIGHT: f32 = 0.5;
+
+/// Score per floor a destination stands on, as attacks from above hit more often and harder.
+const HEIGHT_WEIGHT: f32 = 1.0;
+
+/// Score lost per tile between a destination and the closest opponent.
+const DISTANCE_WEIGHT: f32 = 0.5;
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AiFactions>()
+            .init_resource::<AiTurn>()
+            .add_systems(OnExit(GameState::Battle), systems::reset_ai)
+            .add_systems(
+                Update,
+                (
+                    systems::reset_ai
+                        .run_if(on_event::<TurnStarted>().or_else(on_event::<PhaseChanged>())),
+                    systems::take_turns
+                        .run_if(not(moving()).and_then(not(resource_exists::<Replaying>()))),
+                )
+                    .chain()
+                    .in_set(IssueActions)
+                    .run_if(in_state(GameState::Battle)),
+            );
+    }
+}
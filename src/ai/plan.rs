@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::combat::{damage, hit_chance, Weapon};
+use crate::fog::Sight;
+use crate::map::{Coordinates, Floor, Map, Position};
+use crate::turn::Actions;
+use crate::unit::{Faction, Stats};
+
+use super::{DEALT_WEIGHT, DISTANCE_WEIGHT, HEIGHT_WEIGHT, KILL_BONUS, TAKEN_WEIGHT};
+
+/// A unit as the AI sees it, with its modifiers already applied.
+#[derive(Copy, Clone, Debug)]
+pub struct Combatant {
+    pub entity: Entity,
+    pub faction: Faction,
+    pub position: Position,
+    pub stats: Stats,
+    pub weapon: Weapon,
+    /// How well the faction planning knows where the unit is. Only units in sight can be
+    /// attacked, and units hidden since the faction last looked are left out of the plan.
+    pub sight: Sight,
+}
+
+/// Where a unit should go and whom it should attack once there.
+#[derive(Clone, Debug)]
+pub struct Plan {
+    /// Steps to the destination, empty to stay put.
+    pub path: Vec<Coordinates>,
+    pub target: Option<Coordinates>,
+    pub score: f32,
+}
+
+/// Scores every destination `unit` can reach, and the best target from each, against the
+/// other `combatants` its faction sees or remembers. There are no map objectives yet, so a
+/// destination is scored by its distance to the nearest of those opponents instead.
+pub fn plan(map: &Map, unit: &Combatant, actions: &Actions, combatants: &[Combatant]) -> Plan {
+    let origin = unit.position.coordinates;
+    let jump = Floor(unit.stats.jump);
+
+    let opponents: Vec<&Combatant> = combatants
+        .iter()
+        .filter(|other| other.faction != unit.faction && other.sight != Sight::Unseen)
+        .collect();
+
+    // Where each opponent could stand next turn, following the terrain like any move.
+    let reaches: Vec<HashSet<Coordinates>> = opponents
+        .iter()
+        .map(|opponent| {
+            let from = opponent.position.coordinates;
+            let mut reach =
+                map.reachable(from, opponent.stats.move_range, Floor(opponent.stats.jump));
+            reach.insert(from);
+            reach
+        })
+        .collect();
+
+    let mut destinations: Vec<Coordinates> = Vec::new();
+    if actions.can_move() {
+        destinations.extend(map.reachable(origin, unit.stats.move_range, jump));
+    }
+    // Sorted so ties are broken the same way on every run.
+    destinations.sort_by_key(|coordinates| (coordinates.1, coordinates.0));
+    destinations.insert(0, origin);
+
+    let mut best = Plan {
+        path: Vec::new(),
+        target: None,
+        score: f32::MIN,
+    };
+
+    for destination in destinations {
+        let Some(floor) = map.surface(destination) else {
+            continue;
+        };
+
+        let position = Position {
+            coordinates: destination,
+            floor,
+            order: unit.position.order,
+        };
+
+        let (target, dealt) = if actions.can_act() {
+            best_target(map, unit, &position, &opponents)
+        } else {
+            (None, 0.)
+        };
+
+        let taken: f32 = opponents
+            .iter()
+            .zip(&reaches)
+            .filter(|(opponent, reach)| threatens(opponent, reach, destination))
+            .map(|(opponent, _)| expected_damage(opponent, &opponent.position, &unit.stats, floor))
+            .sum();
+
+        let distance = opponents
+            .iter()
+            .map(|opponent| manhattan(destination, opponent.position.coordinates))
+            .min()
+            .unwrap_or(0);
+
+        let score = dealt * DEALT_WEIGHT - taken * TAKEN_WEIGHT + floor.0 as f32 * HEIGHT_WEIGHT
+            - distance as f32 * DISTANCE_WEIGHT;
+
+        if score <= best.score {
+            continue;
+        }
+
+        let path = if destination == origin {
+            Vec::new()
+        } else {
+            let Some(path) = map.path(origin, destination, jump) else {
+                continue;
+            };
+            path
+        };
+
+        best = Plan {
+            path,
+            target,
+            score,
+        };
+    }
+
+    best
+}
+
+/// The opponent worth attacking most from `position`, and how much that attack is worth.
+fn best_target(
+    map: &Map,
+    unit: &Combatant,
+    position: &Position,
+    opponents: &[&Combatant],
+) -> (Option<Coordinates>, f32) {
+    opponents
+        .iter()
+        .filter(|opponent| {
+            let to = opponent.position.coordinates;
+            opponent.sight == Sight::Visible
+                && unit.weapon.reaches(position.coordinates, to)
+                && unit.weapon.has_clear_shot(map, position, to)
+        })
+        .map(|opponent| {
+            let dealt = expected_damage(unit, position, &opponent.stats, opponent.position.floor);
+            let chance = hit_chance(&opponent.stats, position.floor, opponent.position.floor);
+            let lethal = damage(
+                &unit.stats,
+                &unit.weapon,
+                &opponent.stats,
+                position.floor,
+                opponent.position.floor,
+            ) >= opponent.stats.hp;
+
+            let bonus = if lethal {
+                KILL_BONUS * chance as f32 / 100.
+            } else {
+                0.
+            };
+
+            (Some(opponent.position.coordinates), dealt + bonus)
+        })
+        .fold(
+            (None, 0.),
+            |best, option| if option.1 > best.1 { option } else { best },
+        )
+}
+
+/// Damage `attacker` deals from `from` to a `target` on `target_floor`, weighted by the chance
+/// to hit.
+fn expected_damage(
+    attacker: &Combatant,
+    from: &Position,
+    target: &Stats,
+    target_floor: Floor,
+) -> f32 {
+    let chance = hit_chance(target, from.floor, target_floor);
+    let dealt = damage(
+        &attacker.stats,
+        &attacker.weapon,
+        target,
+        from.floor,
+        target_floor,
+    );
+    chance as f32 / 100. * dealt as f32
+}
+
+/// Whether `opponent` could attack `coordinates` next turn from anywhere in its `reach`.
+fn threatens(opponent: &Combatant, reach: &HashSet<Coordinates>, coordinates: Coordinates) -> bool {
+    reach
+        .iter()
+        .any(|from| manhattan(*from, coordinates) <= opponent.weapon.max_range)
+}
+
+fn manhattan(from: Coordinates, to: Coordinates) -> i32 {
+    let offset = to - from;
+    offset.x.abs() + offset.y.abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::Side;
+
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Coordinates {
+        Coordinates(x, y, Side::Center)
+    }
+
+    fn combatant(map: &Map, index: u32, faction: Faction, coordinates: Coordinates) -> Combatant {
+        Combatant {
+            entity: Entity::from_raw(index),
+            faction,
+            position: Position {
+                coordinates,
+                floor: map.surface(coordinates).unwrap(),
+                ..default()
+            },
+            stats: Stats {
+                max_hp: 20,
+                hp: 20,
+                move_range: 2,
+                jump: 1,
+                attack: 5,
+                ..default()
+            },
+            weapon: Weapon::default(),
+            sight: Sight::Visible,
+        }
+    }
+
+    fn moved() -> Actions {
+        Actions {
+            moved: true,
+            acted: false,
+        }
+    }
+
+    #[test]
+    fn plan_attacks_the_opponent_it_can_finish_off() {
+        let map = Map::from_heights(&[&[0, 0, 0]]);
+        let unit = combatant(&map, 0, Faction::Enemy, at(1, 0));
+        let sturdy = combatant(&map, 1, Faction::Player, at(0, 0));
+        let mut wounded = combatant(&map, 2, Faction::Player, at(2, 0));
+        wounded.stats.hp = 3;
+
+        let planned = plan(&map, &unit, &moved(), &[unit, sturdy, wounded]);
+
+        assert!(planned.path.is_empty());
+        assert_eq!(planned.target, Some(at(2, 0)));
+    }
+
+    #[test]
+    fn plan_climbs_to_high_ground() {
+        let map = Map::from_heights(&[&[0, 0, 0], &[0, 1, 0]]);
+        let unit = combatant(&map, 0, Faction::Enemy, at(0, 0));
+
+        let planned = plan(&map, &unit, &Actions::default(), &[unit]);
+
+        assert_eq!(planned.path.last(), Some(&at(1, 1)));
+        assert_eq!(planned.target, None);
+    }
+
+    #[test]
+    fn plan_has_no_target_out_of_reach() {
+        let map = Map::from_heights(&[&[0, 0, 0, 0, 0, 0, 0]]);
+        let unit = combatant(&map, 0, Faction::Enemy, at(0, 0));
+        let opponent = combatant(&map, 1, Faction::Player, at(6, 0));
+
+        let planned = plan(&map, &unit, &Actions::default(), &[unit, opponent]);
+        assert_eq!(planned.target, None);
+        assert_eq!(
+            planned.path,
+            [at(1, 0), at(2, 0)],
+            "it closes in all the same"
+        );
+
+        let planned = plan(&map, &unit, &moved(), &[unit, opponent]);
+        assert!(planned.path.is_empty());
+        assert_eq!(planned.target, None);
+    }
+
+    #[test]
+    fn plan_leaves_out_opponents_it_cannot_see() {
+        let map = Map::from_heights(&[&[0, 0, 0, 0, 0]]);
+        let unit = combatant(&map, 0, Faction::Enemy, at(2, 0));
+        let mut hidden = combatant(&map, 1, Faction::Player, at(3, 0));
+        hidden.sight = Sight::Unseen;
+
+        let planned = plan(&map, &unit, &Actions::default(), &[unit, hidden]);
+        assert!(planned.path.is_empty(), "nothing to go for");
+        assert_eq!(planned.target, None);
+
+        // A remembered opponent still counts as a threat to keep away from, but isn't attacked.
+        hidden.sight = Sight::Remembered;
+        let planned = plan(&map, &unit, &Actions::default(), &[unit, hidden]);
+        assert!(!planned.path.is_empty());
+        assert_eq!(planned.target, None);
+    }
+}
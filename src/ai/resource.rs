@@ -0,0 +1,26 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::action::Action;
+use crate::unit::Faction;
+
+/// Factions whose phases are played by the AI.
+#[derive(Resource, Debug)]
+pub struct AiFactions(pub HashSet<Faction>);
+
+impl Default for AiFactions {
+    fn default() -> Self {
+        Self(HashSet::from([Faction::Enemy]))
+    }
+}
+
+/// Progress of the AI through the current phase.
+#[derive(Resource, Default, Debug)]
+pub struct AiTurn {
+    /// Actions planned for the current unit, sent one per frame.
+    pub queue: VecDeque<Action>,
+    /// Units that already had their plan made.
+    pub handled: HashSet<Entity>,
+    pub ended: bool,
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::action::Action;
+use crate::combat::Weapon;
+use crate::fog::{FogOfWar, Sight};
+use crate::map::{Map, Position};
+use crate::turn::{Actions, Phase, TurnMode};
+use crate::unit::{Faction, Modifiers, Stats, Unit};
+
+use super::{plan, AiFactions, AiTurn, Combatant};
+
+type UnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Faction,
+        &'static Position,
+        &'static Stats,
+        &'static Modifiers,
+        &'static Weapon,
+        &'static Actions,
+    ),
+    With<Unit>,
+>;
+
+pub fn reset_ai(mut ai: ResMut<AiTurn>) {
+    *ai = AiTurn::default();
+}
+
+/// Plans for one unit of an AI faction at a time and feeds the plan in as actions, ending the
+/// phase once every unit has had its turn.
+#[allow(clippy::too_many_arguments)]
+pub fn take_turns(
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    phase: Res<State<Phase>>,
+    mode: Res<TurnMode>,
+    factions: Res<AiFactions>,
+    mut ai: ResMut<AiTurn>,
+    mut actions: EventWriter<Action>,
+    unit_query: UnitQuery,
+) {
+    let faction = phase.get().faction();
+    if !factions.0.contains(&faction) {
+        return;
+    }
+
+    if let Some(action) = ai.queue.pop_front() {
+        actions.send(action);
+        return;
+    }
+
+    if ai.ended {
+        return;
+    }
+
+    let mut units: Vec<(Combatant, Actions)> = unit_query
+        .iter()
+        .map(
+            |(entity, unit_faction, position, stats, modifiers, weapon, unit_actions)| {
+                let combatant = Combatant {
+                    entity,
+                    faction: *unit_faction,
+                    position: *position,
+                    stats: stats.with(modifiers),
+                    weapon: *weapon,
+                    sight: if *unit_faction == faction {
+                        Sight::Visible
+                    } else {
+                        fog.sight(faction, position.coordinates)
+                    },
+                };
+                (combatant, *unit_actions)
+            },
+        )
+        .collect();
+    units.sort_by_key(|(unit, _)| (unit.position.coordinates.1, unit.position.coordinates.0));
+
+    let next = units.iter().find(|(unit, unit_actions)| {
+        unit.faction == faction
+            && !ai.handled.contains(&unit.entity)
+            && (unit_actions.can_move() || unit_actions.can_act())
+    });
+
+    let Some((unit, unit_actions)) = next else {
+        // The battle's units are spawned a few frames after its first phase has started.
+        if !units.iter().any(|(unit, _)| unit.faction == faction) {
+            return;
+        }
+
+        ai.ended = true;
+
+        // The turn systems end the turn by themselves once everything has been done.
+        let done = |unit_actions: &Actions| match *mode {
+            TurnMode::Phases => unit_actions.acted,
+            TurnMode::ChargeTime => unit_actions.moved && unit_actions.acted,
+        };
+        let waiting = units
+            .iter()
+            .any(|(unit, unit_actions)| unit.faction == faction && !done(unit_actions));
+        if waiting {
+            actions.send(Action::EndTurn);
+        }
+        return;
+    };
+
+    let combatants: Vec<Combatant> = units.iter().map(|(unit, _)| *unit).collect();
+    let plan = plan(&map, unit, unit_actions, &combatants);
+
+    ai.handled.insert(unit.entity);
+    ai.queue
+        .push_back(Action::Select(Some(unit.position.coordinates)));
+    if !plan.path.is_empty() {
+        ai.queue.push_back(Action::Move(plan.path));
+    }
+    if let Some(target) = plan.target {
+        ai.queue.push_back(Action::Attack(target));
+    }
+}
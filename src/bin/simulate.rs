@@ -0,0 +1,273 @@
+//! Plays battles between two AI factions without a window and prints how they went.
+//!
+//! `cargo run --bin simulate -- [battles] [first seed] [phases | charge-time]`
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use tactical_roguelike::action::ActionPlugin;
+use tactical_roguelike::ai::{AiFactions, AiPlugin};
+use tactical_roguelike::combat::{Attacked, CombatPlugin};
+use tactical_roguelike::fog::FogPlugin;
+use tactical_roguelike::map::{CurrentMap, MapPlugin};
+use tactical_roguelike::rng::RngPlugin;
+use tactical_roguelike::run::{Encounter, NodeKind, Party, RunPlugin};
+use tactical_roguelike::state::{GameState, StatePlugin};
+use tactical_roguelike::turn::{TurnMode, TurnNumber, TurnPlugin};
+use tactical_roguelike::unit::{Faction, Unit, UnitPlugin};
+
+const USAGE: &str = "usage: simulate [battles] [first seed] [phases | charge-time]";
+
+const DEFAULT_BATTLES: u64 = 20;
+
+/// Game time that passes every frame, so animations take the same number of frames on any machine.
+const FRAME_TIME: Duration = Duration::from_millis(50);
+
+/// Turns, or activations in charge time, after which a battle is called a draw.
+const MAX_TURNS: u32 = 50;
+
+/// Frames after which a battle that is stuck, rather than just long, is given up on.
+const MAX_FRAMES: u32 = 200_000;
+
+const FACTIONS: [Faction; 2] = [Faction::Player, Faction::Enemy];
+
+#[derive(Default, Clone, Copy, Debug)]
+struct Damage {
+    dealt: i32,
+    hits: u32,
+    misses: u32,
+}
+
+/// What happened in one battle.
+#[derive(Resource, Default, Debug)]
+struct Tally {
+    started: bool,
+    turns: u32,
+    damage: HashMap<Faction, Damage>,
+}
+
+struct Report {
+    /// `None` for a draw.
+    winner: Option<Faction>,
+    tally: Tally,
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let battles = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_BATTLES);
+    let first_seed = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(0);
+    let mode = match args.next().map(|arg| arg.parse()) {
+        None => TurnMode::default(),
+        Some(Ok(mode)) => mode,
+        Some(Err(error)) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut reports = Vec::new();
+    for seed in first_seed..first_seed + battles {
+        match simulate(seed, mode) {
+            Some(report) => {
+                println!(
+                    "seed {}: {} in {} {}",
+                    seed,
+                    describe(report.winner),
+                    report.tally.turns,
+                    turns_of(mode)
+                );
+                reports.push(report);
+            }
+            None => println!("seed {}: gave up after {} frames", seed, MAX_FRAMES),
+        }
+    }
+
+    summarize(&reports, mode);
+}
+
+/// Runs a whole battle on its own app, stepping it by hand until one side is wiped out.
+fn simulate(seed: u64, mode: TurnMode) -> Option<Report> {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((AssetPlugin::default(), ImagePlugin::default(), InputPlugin))
+        .init_asset::<TextureAtlas>()
+        // Read by the mouse cursor, which would otherwise need a window.
+        .add_event::<CursorMoved>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+        .add_plugins(RngPlugin { seed: Some(seed) })
+        .add_plugins((StatePlugin, RunPlugin))
+        .add_plugins((
+            MapPlugin,
+            UnitPlugin,
+            CombatPlugin,
+            TurnPlugin { mode },
+            FogPlugin,
+            ActionPlugin,
+            AiPlugin,
+        ))
+        .insert_resource(AiFactions(HashSet::from(FACTIONS)))
+        .init_resource::<Tally>()
+        .add_systems(
+            Update,
+            (skip_menus, count.run_if(in_state(GameState::Battle))),
+        );
+
+    for _ in 0..MAX_FRAMES {
+        app.update();
+
+        let state = *app.world.resource::<State<GameState>>().get();
+        let tally = app.world.resource::<Tally>();
+
+        let winner = match state {
+            _ if !tally.started => continue,
+            GameState::Battle if tally.turns > MAX_TURNS => None,
+            GameState::Battle => continue,
+            GameState::Defeat => Some(Faction::Enemy),
+            _ => Some(Faction::Player),
+        };
+
+        return Some(Report {
+            winner,
+            tally: app.world.remove_resource::<Tally>()?,
+        });
+    }
+
+    None
+}
+
+/// Goes straight from the main menu to an ordinary battle, once the party is loaded. Stops the
+/// simulator if the party or the map can't be loaded, since every battle would need them.
+fn skip_menus(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    state: Res<State<GameState>>,
+    party: Res<Party>,
+    current_map: Option<Res<CurrentMap>>,
+    tally: Res<Tally>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let failed = party.0.iter().any(|member| {
+        asset_server.recursive_dependency_load_state(&member.kind)
+            == RecursiveDependencyLoadState::Failed
+    }) || current_map.is_some_and(|current_map| {
+        asset_server.recursive_dependency_load_state(&current_map.0)
+            == RecursiveDependencyLoadState::Failed
+    });
+    if failed {
+        eprintln!("could not load the party or the battle map");
+        std::process::exit(1);
+    }
+
+    match state.get() {
+        GameState::MainMenu => next_state.set(GameState::RunMap),
+        GameState::RunMap if !tally.started => {
+            let loaded = party.0.iter().all(|member| {
+                asset_server.recursive_dependency_load_state(&member.kind)
+                    == RecursiveDependencyLoadState::Loaded
+            });
+            if loaded {
+                let encounter = Encounter::new(NodeKind::Battle);
+                commands.insert_resource(CurrentMap(asset_server.load(encounter.map.clone())));
+                commands.insert_resource(encounter);
+                next_state.set(GameState::Battle);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn count(
+    turn_number: Res<TurnNumber>,
+    mut tally: ResMut<Tally>,
+    mut attacked: EventReader<Attacked>,
+    faction_query: Query<&Faction, With<Unit>>,
+) {
+    tally.started = true;
+    tally.turns = turn_number.0;
+
+    for event in attacked.read() {
+        let Ok(faction) = faction_query.get(event.attacker) else {
+            continue;
+        };
+
+        let damage = tally.damage.entry(*faction).or_default();
+        match event.damage {
+            Some(dealt) => {
+                damage.dealt += dealt;
+                damage.hits += 1;
+            }
+            None => damage.misses += 1,
+        }
+    }
+}
+
+fn describe(winner: Option<Faction>) -> String {
+    match winner {
+        Some(faction) => format!("{:?} won", faction),
+        None => "draw".to_string(),
+    }
+}
+
+/// What [`Tally::turns`] counts: player phases, or single activations in charge time.
+fn turns_of(mode: TurnMode) -> &'static str {
+    match mode {
+        TurnMode::Phases => "turns",
+        TurnMode::ChargeTime => "activations",
+    }
+}
+
+fn summarize(reports: &[Report], mode: TurnMode) {
+    if reports.is_empty() {
+        return;
+    }
+
+    let battles = reports.len() as f32;
+    let turns: u32 = reports.iter().map(|report| report.tally.turns).sum();
+
+    println!();
+    println!(
+        "{} battles, {:.1} {} on average",
+        battles,
+        turns as f32 / battles,
+        turns_of(mode)
+    );
+
+    for faction in FACTIONS {
+        let wins = reports
+            .iter()
+            .filter(|report| report.winner == Some(faction))
+            .count();
+
+        let damage = reports
+            .iter()
+            .filter_map(|report| report.tally.damage.get(&faction))
+            .fold(Damage::default(), |total, damage| Damage {
+                dealt: total.dealt + damage.dealt,
+                hits: total.hits + damage.hits,
+                misses: total.misses + damage.misses,
+            });
+        let attacks = (damage.hits + damage.misses).max(1);
+
+        println!(
+            "{:?}: {:.0}% wins, {:.1} damage per battle, {:.0}% hits",
+            faction,
+            wins as f32 / battles * 100.,
+            damage.dealt as f32 / battles,
+            damage.hits as f32 / attacks as f32 * 100.,
+        );
+    }
+
+    let draws = reports
+        .iter()
+        .filter(|report| report.winner.is_none())
+        .count();
+    println!("draws: {:.0}%", draws as f32 / battles * 100.);
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use crate::state::GameState;
+
+mod components;
+mod systems;
+
+pub use components::*;
+
+/// World units the camera pans per second at zoom level 1.
+const PAN_SPEED: f32 = 600.;
+
+const PAN_KEYS: [(KeyCode, Vec2); 4] = [
+    (KeyCode::I, Vec2::Y),
+    (KeyCode::L, Vec2::X),
+    (KeyCode::K, Vec2::NEG_Y),
+    (KeyCode::J, Vec2::NEG_X),
+];
+
+/// Distance, in pixels, from the edge of the window at which the mouse pans the camera.
+const EDGE_MARGIN: f32 = 16.;
+
+const DRAG_BUTTON: MouseButton = MouseButton::Right;
+
+const ZOOM_IN_KEY: KeyCode = KeyCode::Equals;
+
+const ZOOM_OUT_KEY: KeyCode = KeyCode::Minus;
+
+const ROTATE_LEFT_KEY: KeyCode = KeyCode::Q;
+
+const ROTATE_RIGHT_KEY: KeyCode = KeyCode::E;
+
+const ROTATE_LEFT_BUTTON: GamepadButtonType = GamepadButtonType::LeftTrigger;
+
+const ROTATE_RIGHT_BUTTON: GamepadButtonType = GamepadButtonType::RightTrigger;
+
+/// Zoom levels are whole screen pixels per texture pixel, so sprites stay crisp.
+const MIN_ZOOM: i32 = 2;
+
+const MAX_ZOOM: i32 = 8;
+
+/// How quickly the camera catches up with a moving unit, per second.
+const FOLLOW_RATE: f32 = 6.;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, systems::setup).add_systems(
+            Update,
+            (
+                systems::rotate_view,
+                systems::zoom,
+                systems::pan,
+                systems::follow,
+                systems::clamp_to_map,
+            )
+                .chain()
+                .run_if(in_state(GameState::Battle)),
+        );
+    }
+}
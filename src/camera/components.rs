@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Screen pixels per texture pixel.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct Zoom(pub i32);
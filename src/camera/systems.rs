@@ -0,0 +1,213 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+use crate::map::{Map, ViewRotated, SCALE_FACTOR};
+use crate::unit::{Selection, Unit};
+
+use super::{
+    Zoom, DRAG_BUTTON, EDGE_MARGIN, FOLLOW_RATE, MAX_ZOOM, MIN_ZOOM, PAN_KEYS, PAN_SPEED,
+    ROTATE_LEFT_BUTTON, ROTATE_LEFT_KEY, ROTATE_RIGHT_BUTTON, ROTATE_RIGHT_KEY, ZOOM_IN_KEY,
+    ZOOM_OUT_KEY,
+};
+
+pub fn setup(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), Zoom(SCALE_FACTOR as i32)));
+}
+
+/// Turns the map a quarter turn, keeping the camera over the same cell. Waits for any unit
+/// on the move to arrive, as its path is laid out for the old orientation.
+pub fn rotate_view(
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    selection: Res<Selection>,
+    mut map: ResMut<Map>,
+    mut rotated: EventWriter<ViewRotated>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let button_pressed = |button_type: GamepadButtonType| {
+        gamepads
+            .iter()
+            .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    let left = keys.just_pressed(ROTATE_LEFT_KEY) || button_pressed(ROTATE_LEFT_BUTTON);
+    let right = keys.just_pressed(ROTATE_RIGHT_KEY) || button_pressed(ROTATE_RIGHT_BUTTON);
+    if left == right {
+        return;
+    }
+
+    let moving = selection
+        .0
+        .as_ref()
+        .is_some_and(|selected_unit| selected_unit.movement.is_some());
+    if moving {
+        return;
+    }
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let centre = map.point_to_coordinates(transform.translation.truncate());
+
+    map.orientation = if right {
+        map.orientation.clockwise()
+    } else {
+        map.orientation.counterclockwise()
+    };
+
+    let point = map.coordinates_to_point(centre);
+    transform.translation.x = point.x;
+    transform.translation.y = point.y;
+
+    rotated.send(ViewRotated);
+}
+
+pub fn zoom(
+    keys: Res<Input<KeyCode>>,
+    mut scrolled: EventReader<MouseWheel>,
+    mut camera_query: Query<(&mut OrthographicProjection, &mut Zoom)>,
+) {
+    let Ok((mut projection, mut zoom)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let scroll: f32 = scrolled.read().map(|event| event.y).sum();
+    let zoom_in = scroll > 0. || keys.just_pressed(ZOOM_IN_KEY);
+    let zoom_out = scroll < 0. || keys.just_pressed(ZOOM_OUT_KEY);
+    let steps = zoom_in as i32 - zoom_out as i32;
+
+    let level = (zoom.0 + steps).clamp(MIN_ZOOM, MAX_ZOOM);
+    if level != zoom.0 {
+        zoom.0 = level;
+        projection.scale = SCALE_FACTOR / level as f32;
+    }
+}
+
+/// Pans with the pan keys, by holding the mouse at the edge of the window, or by dragging.
+pub fn pan(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window>,
+    mut dragged_from: Local<Option<Vec2>>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
+) {
+    let Ok((mut transform, projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    let mut direction: Vec2 = PAN_KEYS
+        .iter()
+        .filter(|(key, _)| keys.pressed(*key))
+        .map(|(_, direction)| *direction)
+        .sum();
+
+    let cursor = window.cursor_position();
+
+    if let Some(cursor) = cursor.filter(|_| window.focused) {
+        if cursor.x < EDGE_MARGIN {
+            direction.x -= 1.;
+        } else if cursor.x > window.width() - EDGE_MARGIN {
+            direction.x += 1.;
+        }
+
+        // Window coordinates grow downwards.
+        if cursor.y < EDGE_MARGIN {
+            direction.y += 1.;
+        } else if cursor.y > window.height() - EDGE_MARGIN {
+            direction.y -= 1.;
+        }
+    }
+
+    let speed = PAN_SPEED * projection.scale * time.delta_seconds();
+    let offset = direction.normalize_or_zero() * speed;
+    transform.translation += offset.extend(0.);
+
+    if !mouse_buttons.pressed(DRAG_BUTTON) {
+        *dragged_from = None;
+        return;
+    }
+
+    if let (Some(from), Some(to)) = (*dragged_from, cursor) {
+        let delta = (to - from) * projection.scale;
+        transform.translation.x -= delta.x;
+        transform.translation.y += delta.y;
+    }
+    *dragged_from = cursor;
+}
+
+/// Keeps the moving unit in view, easing towards it.
+pub fn follow(
+    time: Res<Time>,
+    selection: Res<Selection>,
+    unit_query: Query<&Transform, (With<Unit>, Without<Camera>)>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let Some(selected_unit) = &selection.0 else {
+        return;
+    };
+
+    if selected_unit.movement.is_none() {
+        return;
+    }
+
+    let (Ok(unit_transform), Ok(mut transform)) = (
+        unit_query.get(selected_unit.entity),
+        camera_query.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    let t = 1. - (-FOLLOW_RATE * time.delta_seconds()).exp();
+    let target = unit_transform.translation.truncate();
+    let translation = transform.translation.truncate().lerp(target, t);
+    transform.translation = translation.extend(transform.translation.z);
+}
+
+/// Stops the view from leaving the map, centring the map on any axis it doesn't fill.
+pub fn clamp_to_map(
+    map: Res<Map>,
+    windows: Query<&Window>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
+) {
+    let (Ok(window), Ok((mut transform, projection))) =
+        (windows.get_single(), camera_query.get_single_mut())
+    else {
+        return;
+    };
+
+    let bounds = map.bounds();
+    let half_view = Vec2::new(window.width(), window.height()) * projection.scale / 2.;
+
+    let clamp = |position: f32, min: f32, max: f32, half_view: f32| {
+        if max - min <= half_view * 2. {
+            (min + max) / 2.
+        } else {
+            position.clamp(min + half_view, max - half_view)
+        }
+    };
+
+    let x = clamp(
+        transform.translation.x,
+        bounds.min.x,
+        bounds.max.x,
+        half_view.x,
+    );
+    let y = clamp(
+        transform.translation.y,
+        bounds.min.y,
+        bounds.max.y,
+        half_view.y,
+    );
+
+    if x != transform.translation.x || y != transform.translation.y {
+        transform.translation.x = x;
+        transform.translation.y = y;
+    }
+}
@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+
+use crate::action::{IssueActions, Replaying};
+use crate::state::GameState;
+use crate::turn::Phase;
+
+mod components;
+mod events;
+mod rules;
+mod systems;
+
+pub use components::*;
+pub use events::*;
+pub use rules::*;
+
+const BASE_HIT: i32 = 90;
+
+const MIN_HIT: i32 = 5;
+
+/// Hit chance gained, in percent, for every floor the attacker stands above the target.
+const HEIGHT_HIT: i32 = 5;
+
+/// Damage multiplier gained for every floor the attacker stands above the target.
+const HEIGHT_DAMAGE: f32 = 0.1;
+
+/// Switches the selected unit between moving and attacking.
+const ATTACK_KEY: KeyCode = KeyCode::F;
+
+const ATTACK_BUTTON: GamepadButtonType = GamepadButtonType::North;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Weapon>()
+            .add_event::<Attacked>()
+            .add_event::<Defeated>()
+            .add_systems(
+                Update,
+                (
+                    systems::toggle_attack.run_if(
+                        in_state(Phase::Player).and_then(not(resource_exists::<Replaying>())),
+                    ),
+                    (systems::click_to_attack, systems::confirm_attack)
+                        .in_set(IssueActions)
+                        .run_if(
+                            in_state(Phase::Player).and_then(not(resource_exists::<Replaying>())),
+                        ),
+                    systems::remove_defeated,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Battle)),
+            );
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::map::{Coordinates, Map, Position, Side};
+
+#[derive(Reflect, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Pattern {
+    /// Every tile within range.
+    #[default]
+    Diamond,
+    /// Only tiles in a straight line from the attacker.
+    Cross,
+}
+
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+#[reflect(Component)]
+pub struct Weapon {
+    pub power: i32,
+    pub min_range: i32,
+    pub max_range: i32,
+    #[serde(default)]
+    pub pattern: Pattern,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            power: 0,
+            min_range: 1,
+            max_range: 1,
+            pattern: Pattern::Diamond,
+        }
+    }
+}
+
+impl Weapon {
+    pub fn reaches(&self, from: Coordinates, to: Coordinates) -> bool {
+        let offset = to - from;
+        let distance = offset.x.abs() + offset.y.abs();
+
+        if distance < self.min_range || distance > self.max_range {
+            return false;
+        }
+
+        match self.pattern {
+            Pattern::Diamond => true,
+            Pattern::Cross => offset.x == 0 || offset.y == 0,
+        }
+    }
+
+    /// Adjacent tiles can always be struck; anything further needs a line of sight.
+    pub fn has_clear_shot(&self, map: &Map, from: &Position, to: Coordinates) -> bool {
+        let offset = to - from.coordinates;
+        offset.x.abs() + offset.y.abs() <= 1 || map.line_of_sight(from, to)
+    }
+
+    pub fn targets(&self, map: &Map, from: &Position) -> HashSet<Coordinates> {
+        let origin = from.coordinates;
+        let range = -self.max_range..=self.max_range;
+
+        range
+            .clone()
+            .flat_map(|x| range.clone().map(move |y| (x, y)))
+            .map(|(x, y)| Coordinates(origin.0 + x, origin.1 + y, Side::Center))
+            .filter(|to| map.in_bounds(*to) && self.reaches(origin, *to))
+            .filter(|to| self.has_clear_shot(map, from, *to))
+            .collect()
+    }
+}
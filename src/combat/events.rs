@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+
+use crate::unit::Faction;
+
+/// Sent for every resolved attack; `damage` is `None` when the attack missed.
+#[derive(Event, Debug)]
+pub struct Attacked {
+    pub attacker: Entity,
+    pub target: Entity,
+    pub damage: Option<i32>,
+}
+
+/// Sent when a unit's HP drops to zero, before it is despawned.
+#[derive(Event, Debug)]
+pub struct Defeated {
+    pub entity: Entity,
+    pub faction: Faction,
+}
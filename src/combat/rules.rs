@@ -0,0 +1,112 @@
+use bracket_lib::prelude::RandomNumberGenerator;
+
+use crate::map::Floor;
+use crate::unit::Stats;
+
+use super::{Weapon, BASE_HIT, HEIGHT_DAMAGE, HEIGHT_HIT, MIN_HIT};
+
+/// Chance, in percent, that an attack from `attacker_floor` hits a target on `target_floor`.
+pub fn hit_chance(target: &Stats, attacker_floor: Floor, target_floor: Floor) -> i32 {
+    let height = attacker_floor.0 - target_floor.0;
+    (BASE_HIT - target.evasion + height * HEIGHT_HIT).clamp(MIN_HIT, 100)
+}
+
+pub fn damage(
+    attacker: &Stats,
+    weapon: &Weapon,
+    target: &Stats,
+    attacker_floor: Floor,
+    target_floor: Floor,
+) -> i32 {
+    let height = attacker_floor.0 - target_floor.0;
+    let base = (attacker.attack + weapon.power - target.defense).max(1) as f32;
+    let multiplier = (1. + height as f32 * HEIGHT_DAMAGE).max(0.);
+
+    ((base * multiplier).round() as i32).max(1)
+}
+
+/// Rolls an attack, returning the damage dealt or `None` on a miss.
+pub fn roll_attack(
+    rng: &mut RandomNumberGenerator,
+    attacker: &Stats,
+    weapon: &Weapon,
+    target: &Stats,
+    attacker_floor: Floor,
+    target_floor: Floor,
+) -> Option<i32> {
+    let chance = hit_chance(target, attacker_floor, target_floor);
+
+    (rng.range(0, 100) < chance)
+        .then(|| damage(attacker, weapon, target, attacker_floor, target_floor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{Rng, Stream};
+
+    fn stats(attack: i32, defense: i32, evasion: i32) -> Stats {
+        Stats {
+            max_hp: 10,
+            hp: 10,
+            attack,
+            defense,
+            evasion,
+            ..Default::default()
+        }
+    }
+
+    fn rolls(rng: &mut RandomNumberGenerator, target: &Stats) -> Vec<Option<i32>> {
+        let (attacker, weapon) = (stats(5, 0, 0), Weapon::default());
+
+        (0..100)
+            .map(|_| roll_attack(rng, &attacker, &weapon, target, Floor(0), Floor(0)))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_rolls_the_same_attacks() {
+        let target = stats(0, 2, 40);
+
+        let first = rolls(Rng::new(9).stream(Stream::Combat), &target);
+        let second = rolls(Rng::new(9).stream(Stream::Combat), &target);
+
+        assert_eq!(first, second);
+        assert!(first.contains(&None) && first.contains(&Some(3)));
+    }
+
+    #[test]
+    fn other_streams_leave_combat_rolls_alone() {
+        let target = stats(0, 2, 40);
+        let mut rng = Rng::new(9);
+        let expected = rolls(Rng::new(9).stream(Stream::Combat), &target);
+
+        rng.stream(Stream::Map).range(0, 100);
+        rng.stream(Stream::Loot).range(0, 100);
+
+        assert_eq!(rolls(rng.stream(Stream::Combat), &target), expected);
+    }
+
+    #[test]
+    fn height_helps_the_attacker() {
+        let (attacker, weapon, target) = (stats(5, 0, 0), Weapon::default(), stats(0, 0, 20));
+
+        assert_eq!(hit_chance(&target, Floor(0), Floor(0)), BASE_HIT - 20);
+        assert_eq!(
+            hit_chance(&target, Floor(2), Floor(0)),
+            BASE_HIT - 20 + 2 * HEIGHT_HIT
+        );
+        assert!(
+            damage(&attacker, &weapon, &target, Floor(3), Floor(0))
+                > damage(&attacker, &weapon, &target, Floor(0), Floor(3))
+        );
+    }
+
+    #[test]
+    fn attacks_always_have_a_chance_and_deal_damage() {
+        let (attacker, weapon, target) = (stats(0, 0, 0), Weapon::default(), stats(0, 9, 200));
+
+        assert_eq!(hit_chance(&target, Floor(0), Floor(5)), MIN_HIT);
+        assert_eq!(damage(&attacker, &weapon, &target, Floor(0), Floor(5)), 1);
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::{
+    input::{mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+};
+
+use crate::action::Action;
+use crate::fog::{FogOfWar, Sight};
+use crate::map::{Coordinates, Map, Position, TileConfirmed};
+use crate::turn::Actions;
+use crate::unit::{Faction, Mode, Selection, Unit, PLAYER_FACTION};
+
+use super::{Defeated, ATTACK_BUTTON, ATTACK_KEY};
+
+pub fn toggle_attack(
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    mut selection: ResMut<Selection>,
+    unit_query: Query<(&Faction, &Actions), With<Unit>>,
+) {
+    let button_pressed = gamepads
+        .iter()
+        .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, ATTACK_BUTTON)));
+
+    if !keys.just_pressed(ATTACK_KEY) && !button_pressed {
+        return;
+    }
+
+    let Some(selected_unit) = &mut selection.0 else {
+        return;
+    };
+
+    if selected_unit.movement.is_some() {
+        return;
+    }
+
+    let Ok((faction, actions)) = unit_query.get(selected_unit.entity) else {
+        return;
+    };
+
+    if *faction != PLAYER_FACTION || !actions.can_act() {
+        return;
+    }
+
+    selected_unit.mode = match selected_unit.mode {
+        Mode::Move => Mode::Attack,
+        Mode::Attack => Mode::Move,
+    };
+}
+
+pub fn click_to_attack(
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    windows_query: Query<&Window>,
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    selection: Res<Selection>,
+    mut mouse_button_input_events: EventReader<MouseButtonInput>,
+    mut actions: EventWriter<Action>,
+) {
+    let Some(selected_unit) = &selection.0 else {
+        return;
+    };
+
+    if selected_unit.mode != Mode::Attack {
+        return;
+    }
+
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    let Some(cursor_position) = windows_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+    else {
+        return;
+    };
+
+    let Some(point) = camera.viewport_to_world_2d(camera_transform, cursor_position) else {
+        return;
+    };
+
+    for event in mouse_button_input_events.read() {
+        if event.button != MouseButton::Left || event.state == ButtonState::Released {
+            continue;
+        }
+
+        let Some(tile) = map.pick(point) else {
+            continue;
+        };
+
+        if targetable(&map, &fog, tile.coordinates) {
+            actions.send(Action::Attack(tile.coordinates));
+            break;
+        }
+    }
+}
+
+pub fn confirm_attack(
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    selection: Res<Selection>,
+    mut confirmed: EventReader<TileConfirmed>,
+    mut actions: EventWriter<Action>,
+) {
+    let Some(selected_unit) = &selection.0 else {
+        return;
+    };
+
+    if selected_unit.mode != Mode::Attack {
+        return;
+    }
+
+    for TileConfirmed(coordinates) in confirmed.read() {
+        if targetable(&map, &fog, *coordinates) {
+            actions.send(Action::Attack(*coordinates));
+            break;
+        }
+    }
+}
+
+/// Whether a unit the player can see stands on `coordinates`; hidden units can't be aimed at.
+fn targetable(map: &Map, fog: &FogOfWar, coordinates: Coordinates) -> bool {
+    map.occupants.contains_key(&coordinates)
+        && fog.sight(PLAYER_FACTION, coordinates) == Sight::Visible
+}
+
+pub fn remove_defeated(
+    mut commands: Commands,
+    mut map: ResMut<Map>,
+    mut selection: ResMut<Selection>,
+    mut defeated: EventReader<Defeated>,
+    position_query: Query<&Position, With<Unit>>,
+) {
+    for event in defeated.read() {
+        if let Ok(position) = position_query.get(event.entity) {
+            if map.occupants.get(&position.coordinates) == Some(&event.entity) {
+                map.occupants.remove(&position.coordinates);
+            }
+        }
+
+        if let Some(selected_unit) = &selection.0 {
+            if selected_unit.entity == event.entity {
+                selection.0 = None;
+            }
+        }
+
+        commands.entity(event.entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+
+use crate::map::MapLoaded;
+use crate::state::GameState;
+
+mod resource;
+mod systems;
+
+pub use resource::*;
+
+/// Tint of tiles that were seen before but aren't in sight anymore.
+const REMEMBERED_TINT: Color = Color::rgb(0.45, 0.45, 0.5);
+
+const UNSEEN_TINT: Color = Color::rgb(0.05, 0.05, 0.08);
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FogOfWar>().add_systems(
+            Update,
+            (
+                systems::reset_fog.run_if(on_event::<MapLoaded>()),
+                systems::update_fog,
+                systems::shade_tiles.run_if(resource_changed::<FogOfWar>()),
+                systems::hide_units.run_if(resource_changed::<FogOfWar>()),
+            )
+                .chain()
+                .run_if(in_state(GameState::Battle)),
+        );
+    }
+}
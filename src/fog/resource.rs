@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::map::{Coordinates, Map};
+use crate::unit::Faction;
+
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Sight {
+    #[default]
+    Unseen,
+    /// Seen before, but not currently in sight of any unit of the faction.
+    Remembered,
+    Visible,
+}
+
+/// What each faction knows of the map.
+#[derive(Resource, Default, Debug)]
+pub struct FogOfWar {
+    cells: HashMap<Faction, HashMap<Coordinates, Sight>>,
+}
+
+impl FogOfWar {
+    pub fn sight(&self, faction: Faction, coordinates: Coordinates) -> Sight {
+        self.cells
+            .get(&faction)
+            .and_then(|cells| cells.get(&coordinates))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Makes `visible` the only cells in sight of `faction`, leaving the rest remembered.
+    pub fn reveal(&mut self, faction: Faction, visible: &HashSet<Coordinates>) {
+        let cells = self.cells.entry(faction).or_default();
+
+        for sight in cells.values_mut() {
+            if *sight == Sight::Visible {
+                *sight = Sight::Remembered;
+            }
+        }
+
+        for coordinates in visible {
+            cells.insert(*coordinates, Sight::Visible);
+        }
+    }
+
+    /// Every cell `faction` has seen, whether or not it's still in sight.
+    pub fn seen(&self, faction: Faction) -> impl Iterator<Item = Coordinates> + '_ {
+        self.cells
+            .get(&faction)
+            .into_iter()
+            .flat_map(|cells| cells.keys().copied())
+    }
+
+    /// Marks `cells` as seen before by `faction`, as when resuming a saved battle.
+    pub fn remember(&mut self, faction: Faction, cells: impl IntoIterator<Item = Coordinates>) {
+        let known = self.cells.entry(faction).or_default();
+
+        for coordinates in cells {
+            known.entry(coordinates).or_insert(Sight::Remembered);
+        }
+    }
+
+    /// The occupants of `map` that `faction` knows about: its own units and the ones in sight.
+    pub fn known_occupants(
+        &self,
+        map: &Map,
+        faction: Faction,
+        faction_of: impl Fn(Entity) -> Option<Faction>,
+    ) -> HashMap<Coordinates, Entity> {
+        map.occupants
+            .iter()
+            .filter(|(coordinates, entity)| {
+                faction_of(**entity) == Some(faction)
+                    || self.sight(faction, **coordinates) == Sight::Visible
+            })
+            .map(|(coordinates, entity)| (*coordinates, *entity))
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+}
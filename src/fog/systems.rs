@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::map::{Coordinates, Map, Position, Tile};
+use crate::unit::{Faction, Modifiers, Stats, Unit, PLAYER_FACTION};
+
+use super::{FogOfWar, Sight, REMEMBERED_TINT, UNSEEN_TINT};
+
+pub fn reset_fog(mut fog: ResMut<FogOfWar>) {
+    fog.clear();
+}
+
+pub fn update_fog(
+    map: Res<Map>,
+    mut fog: ResMut<FogOfWar>,
+    mut removed: RemovedComponents<Unit>,
+    moved_query: Query<(), (With<Unit>, Changed<Position>)>,
+    unit_query: Query<(&Faction, &Position, &Stats, &Modifiers), With<Unit>>,
+) {
+    // Read every removal so the same ones aren't seen again next frame.
+    let removed = removed.read().count() > 0;
+
+    if moved_query.is_empty() && !removed {
+        return;
+    }
+
+    let mut visible: HashMap<Faction, HashSet<Coordinates>> = HashMap::new();
+
+    for (faction, position, stats, modifiers) in &unit_query {
+        let vision = stats.with(modifiers).vision;
+        visible
+            .entry(*faction)
+            .or_default()
+            .extend(map.field_of_view(position, vision));
+    }
+
+    for faction in [Faction::Player, Faction::Enemy] {
+        fog.reveal(faction, &visible.remove(&faction).unwrap_or_default());
+    }
+}
+
+pub fn shade_tiles(
+    fog: Res<FogOfWar>,
+    mut tile_query: Query<(&Position, &mut TextureAtlasSprite), With<Tile>>,
+) {
+    for (position, mut sprite) in &mut tile_query {
+        sprite.color = match fog.sight(PLAYER_FACTION, position.coordinates) {
+            Sight::Visible => Color::WHITE,
+            Sight::Remembered => REMEMBERED_TINT,
+            Sight::Unseen => UNSEEN_TINT,
+        };
+    }
+}
+
+/// Hides units of other factions standing outside the player's sight.
+pub fn hide_units(
+    fog: Res<FogOfWar>,
+    mut unit_query: Query<(&Faction, &Position, &mut Visibility), With<Unit>>,
+) {
+    for (faction, position, mut visibility) in &mut unit_query {
+        let seen = *faction == PLAYER_FACTION
+            || fog.sight(PLAYER_FACTION, position.coordinates) == Sight::Visible;
+
+        *visibility = if seen {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::{Floor, Side};
+
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Coordinates {
+        Coordinates(x, y, Side::Center)
+    }
+
+    fn standing(coordinates: Coordinates) -> Position {
+        Position {
+            coordinates,
+            floor: Floor(0),
+            ..default()
+        }
+    }
+
+    /// A knight at the west end of a corridor cut in two by a stack taller than eye height.
+    fn corridor() -> (World, Schedule, Entity) {
+        let mut world = World::new();
+        world.insert_resource(Map::from_heights(&[&[0, 0, 2, 0, 0, 0]]));
+        world.init_resource::<FogOfWar>();
+
+        let stats = Stats {
+            max_hp: 20,
+            hp: 20,
+            vision: 4,
+            ..default()
+        };
+        let knight = world
+            .spawn((
+                Unit,
+                Faction::Player,
+                standing(at(0, 0)),
+                stats,
+                Modifiers::default(),
+            ))
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_fog);
+        schedule.run(&mut world);
+
+        (world, schedule, knight)
+    }
+
+    fn sights(world: &World, faction: Faction) -> Vec<Sight> {
+        let fog = world.resource::<FogOfWar>();
+        (0..6).map(|x| fog.sight(faction, at(x, 0))).collect()
+    }
+
+    #[test]
+    fn tiles_start_unseen_then_turn_visible_then_remembered() {
+        let (mut world, mut schedule, knight) = corridor();
+        let fog = world.resource::<FogOfWar>();
+        assert_eq!(fog.sight(Faction::Player, at(1, 0)), Sight::Visible);
+        assert_eq!(fog.sight(Faction::Player, at(4, 0)), Sight::Unseen);
+
+        *world.get_mut::<Position>(knight).unwrap() = standing(at(3, 0));
+        schedule.run(&mut world);
+
+        let fog = world.resource::<FogOfWar>();
+        assert_eq!(fog.sight(Faction::Player, at(1, 0)), Sight::Remembered);
+        assert_eq!(fog.sight(Faction::Player, at(4, 0)), Sight::Visible);
+    }
+
+    #[test]
+    fn tiles_behind_a_tall_stack_stay_unseen() {
+        let (world, ..) = corridor();
+
+        assert_eq!(
+            sights(&world, Faction::Player),
+            [
+                Sight::Visible,
+                Sight::Visible,
+                Sight::Visible,
+                Sight::Unseen,
+                Sight::Unseen,
+                Sight::Unseen,
+            ]
+        );
+    }
+
+    #[test]
+    fn factions_only_see_through_their_own_units() {
+        let (world, ..) = corridor();
+
+        assert!(sights(&world, Faction::Enemy)
+            .iter()
+            .all(|sight| *sight == Sight::Unseen));
+    }
+}
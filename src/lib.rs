@@ -0,0 +1,13 @@
+pub mod action;
+pub mod ai;
+pub mod camera;
+pub mod combat;
+pub mod fog;
+pub mod map;
+pub mod rng;
+pub mod run;
+pub mod save;
+pub mod state;
+pub mod turn;
+pub mod unit;
+pub mod window;
@@ -1,18 +1,43 @@
 use bevy::prelude::*;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-use crate::map::MapPlugin;
-use crate::unit::UnitPlugin;
-use crate::window::DisplayPlugin;
-
-mod map;
-mod unit;
-mod window;
+use tactical_roguelike::action::ActionPlugin;
+use tactical_roguelike::ai::AiPlugin;
+use tactical_roguelike::camera::CameraPlugin;
+use tactical_roguelike::combat::CombatPlugin;
+use tactical_roguelike::fog::FogPlugin;
+use tactical_roguelike::map::MapPlugin;
+use tactical_roguelike::rng::RngPlugin;
+use tactical_roguelike::run::RunPlugin;
+use tactical_roguelike::save::SavePlugin;
+use tactical_roguelike::state::StatePlugin;
+use tactical_roguelike::turn::TurnPlugin;
+use tactical_roguelike::unit::UnitPlugin;
+use tactical_roguelike::window::DisplayPlugin;
 
 fn main() {
     App::new()
-        .add_plugins(DisplayPlugin)
+        .add_plugins((DisplayPlugin, CameraPlugin))
         .add_plugins(WorldInspectorPlugin::new())
-        .add_plugins((MapPlugin, UnitPlugin))
+        .add_plugins(RngPlugin {
+            seed: std::env::var("SEED")
+                .ok()
+                .and_then(|seed| seed.parse().ok()),
+        })
+        .add_plugins((StatePlugin, RunPlugin, SavePlugin))
+        .add_plugins((
+            MapPlugin,
+            UnitPlugin,
+            CombatPlugin,
+            TurnPlugin {
+                mode: std::env::var("TURN_MODE")
+                    .ok()
+                    .and_then(|mode| mode.parse().ok())
+                    .unwrap_or_default(),
+            },
+            FogPlugin,
+            ActionPlugin,
+            AiPlugin,
+        ))
         .run();
 }
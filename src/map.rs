@@ -2,14 +2,25 @@ use bevy::prelude::*;
 
 use cursor::CursorPlugin;
 
+use crate::state::GameState;
+
 mod components;
 mod cursor;
+mod data;
+mod events;
+mod generator;
+mod pathfinding;
 mod resource;
+mod sight;
 mod systems;
 mod tile;
 
 pub use components::*;
 pub use cursor::components::*;
+pub use data::*;
+pub use events::*;
+pub use generator::*;
+pub use pathfinding::*;
 pub use resource::*;
 pub use tile::{bundle::*, components::*};
 
@@ -17,13 +28,28 @@ pub const MAP_SIZE: f32 = 11.;
 pub const TILE_SIZE: Vec2 = Vec2::new(16.0, 17.0);
 pub const SCALE_FACTOR: f32 = 4.;
 
+/// Height, in floors, above the surface a unit sees from and is seen at.
+const EYE_HEIGHT: f32 = 0.5;
+
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Map::default())
+            .init_asset::<MapData>()
+            .init_asset_loader::<MapLoader>()
+            .add_event::<MapLoaded>()
+            .add_event::<ViewRotated>()
             .add_plugins(CursorPlugin)
-            .add_systems(Startup, systems::setup);
-        // .add_systems(Update, (systems::update_z_index));
+            .add_systems(Startup, systems::setup)
+            .add_systems(
+                Update,
+                (
+                    systems::spawn_map,
+                    systems::reorient.run_if(on_event::<ViewRotated>()),
+                )
+                    .run_if(in_state(GameState::Battle)),
+            )
+            .add_systems(OnExit(GameState::Battle), systems::reset_map);
     }
 }
@@ -2,8 +2,9 @@ use std::hash::{Hash, Hasher};
 use std::ops::{Add, Sub};
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
 pub enum Side {
     Left,
     #[default]
@@ -11,7 +12,7 @@ pub enum Side {
     Right,
 }
 
-#[derive(Component, Copy, Clone, Default, Debug)]
+#[derive(Component, Serialize, Deserialize, Copy, Clone, Default, Debug)]
 pub struct Coordinates(pub i32, pub i32, pub Side);
 
 impl Hash for Coordinates {
@@ -1,14 +1,63 @@
 use bevy::prelude::*;
 
+use crate::state::GameState;
+
+use super::{Highlights, TileCancelled, TileConfirmed, TileCursor};
+
 mod bundle;
 pub mod components;
 mod systems;
 
+/// Lifts the hover cursor onto the top face of the tile it points at.
+const HOVER_OFFSET: f32 = 5.5;
+
+const NAVIGATION_KEYS: [(KeyCode, Vec2); 8] = [
+    (KeyCode::Up, Vec2::Y),
+    (KeyCode::W, Vec2::Y),
+    (KeyCode::Right, Vec2::X),
+    (KeyCode::D, Vec2::X),
+    (KeyCode::Down, Vec2::NEG_Y),
+    (KeyCode::S, Vec2::NEG_Y),
+    (KeyCode::Left, Vec2::NEG_X),
+    (KeyCode::A, Vec2::NEG_X),
+];
+
+const NAVIGATION_BUTTONS: [(GamepadButtonType, Vec2); 4] = [
+    (GamepadButtonType::DPadUp, Vec2::Y),
+    (GamepadButtonType::DPadRight, Vec2::X),
+    (GamepadButtonType::DPadDown, Vec2::NEG_Y),
+    (GamepadButtonType::DPadLeft, Vec2::NEG_X),
+];
+
+const CONFIRM_KEY: KeyCode = KeyCode::Space;
+
+const CONFIRM_BUTTON: GamepadButtonType = GamepadButtonType::South;
+
+const CANCEL_KEY: KeyCode = KeyCode::X;
+
+const CANCEL_BUTTON: GamepadButtonType = GamepadButtonType::East;
+
 pub struct CursorPlugin;
 
 impl Plugin for CursorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, systems::setup)
-            .add_systems(Update, systems::hovering);
+        app.init_resource::<Highlights>()
+            .init_resource::<TileCursor>()
+            .add_event::<TileConfirmed>()
+            .add_event::<TileCancelled>()
+            .add_systems(Startup, systems::setup)
+            .add_systems(OnEnter(GameState::Battle), systems::spawn_cursors)
+            .add_systems(
+                Update,
+                (
+                    systems::point_with_mouse,
+                    systems::navigate,
+                    systems::hovering,
+                    systems::place_cursor.run_if(resource_changed::<TileCursor>()),
+                    systems::highlight_tiles.run_if(resource_changed::<Highlights>()),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Battle)),
+            );
     }
 }
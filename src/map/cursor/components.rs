@@ -42,3 +42,37 @@ impl Cursor for SelectCursor {
         &self.0
     }
 }
+
+/// Marks a tile inside the selected unit's range.
+#[derive(Component)]
+pub struct RangeCursor(pub Data);
+
+impl Cursor for RangeCursor {
+    fn new() -> Self {
+        Self(Data {
+            index: 2,
+            rect: Rect::new(16., 8., 32., 15.),
+        })
+    }
+
+    fn data(&self) -> &Data {
+        &self.0
+    }
+}
+
+/// Marks a tile the selected unit can attack.
+#[derive(Component)]
+pub struct AttackCursor(pub Data);
+
+impl Cursor for AttackCursor {
+    fn new() -> Self {
+        Self(Data {
+            index: 3,
+            rect: Rect::new(0., 8., 16., 15.),
+        })
+    }
+
+    fn data(&self) -> &Data {
+        &self.0
+    }
+}
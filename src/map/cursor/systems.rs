@@ -1,33 +1,103 @@
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 
-use crate::map::resource::Map;
-use crate::map::{Coordinates, Cursor, Floor, HoverCursor, Order, Position, SelectCursor, SCALE_FACTOR, Side};
+use crate::map::resource::{Highlight, Highlights, Indicators, Map, Pointing, TileCursor};
+use crate::map::{
+    AttackCursor, Coordinates, Cursor, Floor, HoverCursor, Order, Position, RangeCursor,
+    SelectCursor, Side, TileCancelled, TileConfirmed, SCALE_FACTOR,
+};
+
+use crate::state::{DespawnOnExit, GameState};
 
 use super::bundle::CursorBundle;
+use super::{
+    CANCEL_BUTTON, CANCEL_KEY, CONFIRM_BUTTON, CONFIRM_KEY, HOVER_OFFSET, NAVIGATION_BUTTONS,
+    NAVIGATION_KEYS,
+};
+
+type RangeQuery<'w, 's> = Query<'w, 's, Entity, Or<(With<RangeCursor>, With<AttackCursor>)>>;
 
 pub fn setup(
     asset_server: Res<AssetServer>,
-    map: Res<Map>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut commands: Commands,
 ) {
     let texture_handle = asset_server.load("textures/TRPGIsometricAssetPack_MapIndicators.png");
     let mut texture_atlas = TextureAtlas::new_empty(texture_handle, Vec2::new(32., 25.));
 
+    texture_atlas.add_texture(HoverCursor::new().0.rect);
+    texture_atlas.add_texture(SelectCursor::new().0.rect);
+    texture_atlas.add_texture(RangeCursor::new().0.rect);
+    texture_atlas.add_texture(AttackCursor::new().0.rect);
+
+    commands.insert_resource(Indicators(texture_atlases.add(texture_atlas)));
+}
+
+pub fn spawn_cursors(mut commands: Commands, map: Res<Map>, indicators: Res<Indicators>) {
     let mut hover_bundle = cursor_bundle::<HoverCursor>(&map);
-    texture_atlas.add_texture(hover_bundle.cursor.0.rect);
+    hover_bundle.sprite.texture_atlas = indicators.0.clone();
 
     let mut select_bundle = cursor_bundle::<SelectCursor>(&map);
-    texture_atlas.add_texture(select_bundle.cursor.0.rect);
+    select_bundle.sprite.texture_atlas = indicators.0.clone();
 
-    let texture_atlas_handle = &texture_atlases.add(texture_atlas);
+    commands.spawn((hover_bundle, DespawnOnExit(GameState::Battle)));
+    commands.spawn((select_bundle, DespawnOnExit(GameState::Battle)));
+}
 
-    hover_bundle.sprite.texture_atlas = texture_atlas_handle.clone();
-    select_bundle.sprite.texture_atlas = texture_atlas_handle.clone();
+pub fn highlight_tiles(
+    mut commands: Commands,
+    map: Res<Map>,
+    highlights: Res<Highlights>,
+    indicators: Res<Indicators>,
+    range_query: RangeQuery,
+) {
+    range_query.iter().for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+
+    for (coordinates, highlight) in &highlights.0 {
+        let Some(floor) = map.surface(*coordinates) else {
+            continue;
+        };
+
+        let position = Position {
+            coordinates: *coordinates,
+            floor,
+            order: Order(0.5),
+        };
+
+        match highlight {
+            Highlight::Move => {
+                commands.spawn((
+                    highlight_bundle::<RangeCursor>(&map, &indicators, position),
+                    DespawnOnExit(GameState::Battle),
+                ));
+            }
+            Highlight::Attack => {
+                commands.spawn((
+                    highlight_bundle::<AttackCursor>(&map, &indicators, position),
+                    DespawnOnExit(GameState::Battle),
+                ));
+            }
+        }
+    }
+}
 
-    commands.spawn(hover_bundle);
-    commands.spawn(select_bundle);
+fn highlight_bundle<T>(
+    map: &Res<Map>,
+    indicators: &Indicators,
+    position: Position,
+) -> CursorBundle<T>
+where
+    T: Cursor,
+{
+    let mut bundle = cursor_bundle::<T>(map);
+    bundle.position = position;
+    bundle.sprite.texture_atlas = indicators.0.clone();
+    bundle.sprite.transform.translation = map.position_to_translation(&position);
+    bundle.sprite.transform.translation.y -= 1.5;
+    bundle.sprite.visibility = Visibility::Visible;
+    bundle
 }
 
 pub fn cursor_bundle<T>(map: &Res<Map>) -> CursorBundle<T>
@@ -62,124 +132,134 @@ where
     }
 }
 
-pub fn hovering(
-    camera_query: Query<(&Camera, &GlobalTransform)>,
-    windows: Query<&Window>,
+pub fn point_with_mouse(
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut tile_cursor: ResMut<TileCursor>,
+) {
+    if cursor_moved.read().count() > 0 && tile_cursor.pointing != Pointing::Mouse {
+        tile_cursor.pointing = Pointing::Mouse;
+    }
+}
+
+/// Moves the hover cursor a tile at a time with the arrow keys, WASD or a d-pad, and turns
+/// confirm and cancel into [`TileConfirmed`] and [`TileCancelled`].
+pub fn navigate(
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
     map: Res<Map>,
-    position_query: Query<&Position>,
+    mut tile_cursor: ResMut<TileCursor>,
+    mut confirmed: EventWriter<TileConfirmed>,
+    mut cancelled: EventWriter<TileCancelled>,
+) {
+    let button_pressed = |button_type: GamepadButtonType| {
+        gamepads
+            .iter()
+            .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    let direction: Vec2 = NAVIGATION_KEYS
+        .iter()
+        .filter(|(key, _)| keys.just_pressed(*key))
+        .map(|(_, direction)| *direction)
+        .chain(
+            NAVIGATION_BUTTONS
+                .iter()
+                .filter(|(button_type, _)| button_pressed(*button_type))
+                .map(|(_, direction)| *direction),
+        )
+        .sum();
+
+    if direction != Vec2::ZERO {
+        let step = map.screen_step(direction);
+        let coordinates = tile_cursor.coordinates;
+        let next = Coordinates(coordinates.0 + step.x, coordinates.1 + step.y, Side::Center);
+
+        tile_cursor.pointing = Pointing::Buttons;
+        if map.in_bounds(next) {
+            tile_cursor.coordinates = next;
+        }
+    }
+
+    if keys.just_pressed(CONFIRM_KEY) || button_pressed(CONFIRM_BUTTON) {
+        tile_cursor.pointing = Pointing::Buttons;
+        confirmed.send(TileConfirmed(tile_cursor.coordinates));
+    }
+
+    if keys.just_pressed(CANCEL_KEY) || button_pressed(CANCEL_BUTTON) {
+        cancelled.send(TileCancelled);
+    }
+}
+
+/// Puts the hover cursor on top of the stack at [`TileCursor`] while keys or a gamepad drive it.
+pub fn place_cursor(
+    map: Res<Map>,
+    tile_cursor: Res<TileCursor>,
     mut cursor_query: Query<(&mut Transform, &mut Visibility, &Position), With<HoverCursor>>,
 ) {
-    let (camera, camera_transform) = camera_query.single();
+    if tile_cursor.pointing != Pointing::Buttons {
+        return;
+    }
 
-    let Some(cursor_position) = windows.single().cursor_position() else {
+    let Ok((mut transform, mut visibility, cursor_position)) = cursor_query.get_single_mut() else {
         return;
     };
 
-    let Some(point) = camera.viewport_to_world_2d(camera_transform, cursor_position) else {
+    let Some(floor) = map.surface(tile_cursor.coordinates) else {
+        *visibility = Visibility::Hidden;
         return;
     };
 
-    info!("point: {point:?}");
+    transform.translation = map.position_to_translation(&Position {
+        coordinates: tile_cursor.coordinates,
+        floor,
+        order: cursor_position.order,
+    });
+    transform.translation.y -= HOVER_OFFSET;
+    *visibility = Visibility::Visible;
+}
 
-    let (mut transform, mut visibility, cursor_position) = cursor_query.single_mut();
+pub fn hovering(
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window>,
+    map: Res<Map>,
+    mut tile_cursor: ResMut<TileCursor>,
+    mut cursor_query: Query<(&mut Transform, &mut Visibility, &Position), With<HoverCursor>>,
+) {
+    if tile_cursor.pointing != Pointing::Mouse {
+        return;
+    }
 
-    let mut mouse_coordinates = map.point_to_coordinates(point);
-
-    info!("mouse_coordinates: {mouse_coordinates:?}");
-
-    *visibility = Visibility::Hidden;
-
-    // if let Some(entities) = map.tiles.get(&mouse_coordinates) {
-    //     let positions = entities
-    //         .iter()
-    //         .map(|entity| position_query.get(*entity).unwrap())
-    //         .collect::<Vec<&Position>>();
-    //
-    //     let position = positions.iter().max_by_key(|position| position.floor);
-    //
-    //     if let Some(position) = position {
-    //         transform.translation = map.position_to_translation(&Position {
-    //             coordinates: position.coordinates,
-    //             floor: position.floor,
-    //             order: cursor_position.order,
-    //         });
-    //         transform.translation.y -= 5.5;
-    //         *visibility = Visibility::Visible;
-    //     }
-    // }
-
-    // deu ruim, montar map com todas a posições resolvidas
-
-    fn bla(coordinates: Coordinates, map: &Map, position_query: &Query<&Position>) -> Option<(Coordinates, Floor)> {
-    // fazer função recursiva
-    for i in (0..=2).rev() {
-        let mut floor = Floor(i);
-        // info!("coordinates {:?}", coordinates);
-        // info!("floor {:?}", floor);
-        let mut coordinates = coordinates + floor;
-
-            if !map.in_bounds(coordinates) {
-                continue;
-            }
+    // Nothing to point at when running without a window, as the simulator does.
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
 
-            if let Some(entities) = map.tiles.get(&coordinates) {
-                let positions = entities.iter().map(|entity| {
-                    position_query.get(*entity).unwrap()
-                }).collect::<Vec<&Position>>();
-
-                let position = positions.iter().max_by_key(|position| { position.floor });
-
-                if let Some(position) = position {
-                    info!("floor {:?}", floor);
-                    info!("position {:?}", position);
-
-                    if position.floor < floor {
-                        continue;
-                    }
-
-                    if floor < position.floor {
-                        // info!("coordinates before {:?}", coordinates);
-                        match coordinates.2 {
-                            Side::Left => coordinates.1 += position.floor.0,
-                            Side::Right => coordinates.0 += position.floor.0,
-                            Side::Center => ()
-                        };
-                        // info!("coordinates after {:?}", coordinates);
-                        // coordinates.1 += 1;
-                        // coordinates.0 += 1;
-                        // return bla(coordinates, map, position_query);
-                        return Some((coordinates, floor));
-                    }
-
-                    if map.in_bounds(coordinates) {
-                        return Some((coordinates, floor));
-                    }
-                }
-            }
-        }
+    let Some(cursor_position) = windows.get_single().ok().and_then(Window::cursor_position) else {
+        return;
+    };
 
-        None
-    }
+    let Some(point) = camera.viewport_to_world_2d(camera_transform, cursor_position) else {
+        return;
+    };
 
+    let (mut transform, mut visibility, cursor_position) = cursor_query.single_mut();
 
-    let Some((coordinates, floor)) = bla(mouse_coordinates, &map, &position_query) else {
+    let Some(tile) = map.pick(point) else {
+        *visibility = Visibility::Hidden;
         return;
     };
 
-    if map.in_bounds(coordinates) {
-        transform.translation = map.position_to_translation(&Position {
-            coordinates,
-            floor,
-            order: cursor_position.order,
-        });
-        transform.translation.y -= 5.5;
-        *visibility = Visibility::Visible;
+    transform.translation = map.position_to_translation(&Position {
+        order: cursor_position.order,
+        ..tile
+    });
+    transform.translation.y -= HOVER_OFFSET;
+    *visibility = Visibility::Visible;
+
+    // Keys pick up from wherever the mouse left off.
+    let surface = Coordinates(tile.coordinates.0, tile.coordinates.1, Side::Center);
+    if tile_cursor.coordinates != surface {
+        tile_cursor.coordinates = surface;
     }
-
-    // let new_point = map.coordinates_to_point(mouse_coordinates);
-    //
-    // transform.translation.x = new_point.x;
-    // transform.translation.y = new_point.y - 1.5;
-    //
-    // *visibility = Visibility::Visible
 }
@@ -0,0 +1,121 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::unit::UnitKind;
+
+use super::{Coordinates, Floor, MapGenerator, Side};
+
+/// A battlefield as authored in `assets/maps/*.map.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct MapData {
+    pub size: (i32, i32),
+    /// Left empty when the cells come from the `generator`.
+    #[serde(default)]
+    pub cells: Vec<CellData>,
+    pub spawns: Vec<SpawnData>,
+    #[serde(default)]
+    pub generator: Option<MapGenerator>,
+}
+
+/// The stack of tiles on one cell, listed bottom to top.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CellData {
+    pub coordinates: (i32, i32),
+    pub stack: Vec<TileData>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+pub struct TileData {
+    pub index: usize,
+    pub floor: i32,
+}
+
+/// Which side of the battle a spawn is for; the units decide what faction that makes them.
+#[derive(Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SpawnSide {
+    Player,
+    Enemy,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnData {
+    pub coordinates: (i32, i32),
+    pub side: SpawnSide,
+    /// Path to the [`UnitKind`] spawned here.
+    pub kind: String,
+    #[serde(skip)]
+    pub handle: Handle<UnitKind>,
+}
+
+impl CellData {
+    pub fn coordinates(&self) -> Coordinates {
+        Coordinates(self.coordinates.0, self.coordinates.1, Side::Center)
+    }
+}
+
+impl TileData {
+    pub fn floor(&self) -> Floor {
+        Floor(self.floor)
+    }
+}
+
+impl SpawnData {
+    pub fn coordinates(&self) -> Coordinates {
+        Coordinates(self.coordinates.0, self.coordinates.1, Side::Center)
+    }
+}
+
+#[derive(Default)]
+pub struct MapLoader;
+
+#[derive(Debug, Error)]
+pub enum MapLoaderError {
+    #[error("could not read map file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse map file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("{0:?} is outside of a {1}x{2} map")]
+    OutOfBounds((i32, i32), i32, i32),
+}
+
+impl AssetLoader for MapLoader {
+    type Asset = MapData;
+    type Settings = ();
+    type Error = MapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut data = ron::de::from_bytes::<MapData>(&bytes)?;
+
+            let (width, height) = data.size;
+            let in_bounds = |(x, y): (i32, i32)| x >= 0 && x < width && y >= 0 && y < height;
+
+            let cells = data.cells.iter().map(|cell| cell.coordinates);
+            let spawns = data.spawns.iter().map(|spawn| spawn.coordinates);
+            if let Some(coordinates) = cells.chain(spawns).find(|c| !in_bounds(*c)) {
+                return Err(MapLoaderError::OutOfBounds(coordinates, width, height));
+            }
+
+            for spawn in &mut data.spawns {
+                spawn.handle = load_context.load(spawn.kind.clone());
+            }
+
+            Ok(data)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
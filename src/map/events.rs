@@ -0,0 +1,20 @@
+use bevy::prelude::*;
+
+use super::Coordinates;
+
+/// Sent once every tile of the current [`MapData`](super::MapData) has been spawned.
+#[derive(Event)]
+pub struct MapLoaded;
+
+/// Sent after [`Map::orientation`](super::Map::orientation) changes, so everything drawn on
+/// the grid can be put back in place.
+#[derive(Event)]
+pub struct ViewRotated;
+
+/// Confirm pressed with the [`HoverCursor`](super::HoverCursor) on a tile, the key and gamepad
+/// counterpart of a click.
+#[derive(Event, Debug)]
+pub struct TileConfirmed(pub Coordinates);
+
+#[derive(Event, Debug)]
+pub struct TileCancelled;
@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bracket_lib::prelude::{FastNoise, FractalType, NoiseType};
+use serde::Deserialize;
+
+use super::{
+    CellData, Coordinates, Floor, Map, Order, Position, TileData, SCALE_FACTOR, TILE_SIZE,
+};
+
+const GROUND_TILE: usize = 92;
+
+const RAISED_TILE: usize = 93;
+
+/// Highest step every unit can take, so spawn zones are connected for all of them.
+const GENERATOR_JUMP: Floor = Floor(1);
+
+const NOISE_FREQUENCY: f32 = 0.15;
+
+const NOISE_OCTAVES: i32 = 3;
+
+/// Builds the tile stacks of a battlefield from noise instead of a hand-made layout.
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub struct MapGenerator {
+    /// Drawn from the map stream of the run whenever a battle is built.
+    #[serde(skip)]
+    pub seed: u64,
+    /// Highest floor a stack may reach.
+    pub max_height: i32,
+}
+
+impl MapGenerator {
+    /// The stacks of a `size` map in which every spawn can walk to every other one.
+    pub fn generate(&self, size: (i32, i32), spawns: &[Coordinates]) -> Vec<CellData> {
+        let mut heights = self.heights(size);
+
+        // Each pass carves at least one more spawn into reach of the first one.
+        for _ in 0..spawns.len() {
+            let Some(unreachable) = first_unreachable(size, &heights, spawns) else {
+                break;
+            };
+            carve(size, &mut heights, spawns[0], unreachable);
+        }
+
+        if first_unreachable(size, &heights, spawns).is_some() {
+            heights.iter_mut().for_each(|height| *height = 0);
+        }
+
+        heights
+            .iter()
+            .enumerate()
+            .map(|(idx, height)| CellData {
+                coordinates: (idx as i32 % size.0, idx as i32 / size.0),
+                stack: stack(*height),
+            })
+            .collect()
+    }
+
+    fn heights(&self, size: (i32, i32)) -> Vec<i32> {
+        let mut noise = FastNoise::seeded(self.seed);
+        noise.set_noise_type(NoiseType::PerlinFractal);
+        noise.set_fractal_type(FractalType::FBM);
+        noise.set_fractal_octaves(NOISE_OCTAVES);
+        noise.set_frequency(NOISE_FREQUENCY);
+
+        let levels = (self.max_height + 1) as f32;
+
+        (0..size.1)
+            .flat_map(|y| (0..size.0).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let value = (noise.get_noise(x as f32, y as f32) + 1.) / 2.;
+                ((value * levels) as i32).clamp(0, self.max_height)
+            })
+            .collect()
+    }
+}
+
+fn first_unreachable(
+    size: (i32, i32),
+    heights: &[i32],
+    spawns: &[Coordinates],
+) -> Option<Coordinates> {
+    let (first, rest) = spawns.split_first()?;
+
+    let map = surfaces(size, heights);
+    let reachable: HashSet<Coordinates> = map.reachable(*first, size.0 * size.1, GENERATOR_JUMP);
+
+    rest.iter()
+        .find(|spawn| *spawn != first && !reachable.contains(*spawn))
+        .copied()
+}
+
+/// A [`Map`] with only the surface of each stack, good enough for pathfinding.
+fn surfaces(size: (i32, i32), heights: &[i32]) -> Map {
+    let mut map = Map::new(
+        Vec2::new(size.0 as f32, size.1 as f32),
+        TILE_SIZE,
+        SCALE_FACTOR,
+    );
+
+    for (idx, height) in heights.iter().enumerate() {
+        let position = Position {
+            coordinates: map.index_to_coordinates(idx),
+            floor: Floor(*height),
+            order: Order(0.),
+        };
+        map.insert_tile(&position, Entity::PLACEHOLDER);
+    }
+
+    map
+}
+
+/// Evens out heights along a straight walk from `from` to `to` so it can be climbed.
+fn carve(size: (i32, i32), heights: &mut [i32], from: Coordinates, to: Coordinates) {
+    let idx = |x: i32, y: i32| (y * size.0 + x) as usize;
+
+    let horizontal = (from.0.min(to.0)..=from.0.max(to.0)).map(|x| (x, from.1));
+    let vertical = (from.1.min(to.1)..=from.1.max(to.1)).map(|y| (to.0, y));
+    let mut walk: Vec<(i32, i32)> = if from.0 <= to.0 {
+        horizontal.collect()
+    } else {
+        horizontal.rev().collect()
+    };
+    if from.1 <= to.1 {
+        walk.extend(vertical);
+    } else {
+        walk.extend(vertical.rev());
+    }
+
+    let mut previous = heights[idx(from.0, from.1)];
+    for (x, y) in walk {
+        let height = &mut heights[idx(x, y)];
+        *height = (*height).clamp(previous - GENERATOR_JUMP.0, previous + GENERATOR_JUMP.0);
+        previous = *height;
+    }
+}
+
+fn stack(height: i32) -> Vec<TileData> {
+    (0..=height)
+        .map(|floor| TileData {
+            index: if floor == 0 { GROUND_TILE } else { RAISED_TILE },
+            floor,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Side;
+
+    const SIZE: (i32, i32) = (12, 10);
+
+    fn spawns() -> Vec<Coordinates> {
+        vec![
+            Coordinates(0, 0, Side::Center),
+            Coordinates(11, 9, Side::Center),
+            Coordinates(0, 9, Side::Center),
+            Coordinates(11, 0, Side::Center),
+        ]
+    }
+
+    fn heights_of(cells: &[CellData]) -> Vec<i32> {
+        cells
+            .iter()
+            .map(|cell| cell.stack.last().map_or(0, |tile| tile.floor))
+            .collect()
+    }
+
+    #[test]
+    fn every_spawn_is_reachable() {
+        for seed in 0..50 {
+            let generator = MapGenerator {
+                seed,
+                max_height: 4,
+            };
+            let cells = generator.generate(SIZE, &spawns());
+
+            assert_eq!(cells.len(), (SIZE.0 * SIZE.1) as usize);
+            assert_eq!(
+                first_unreachable(SIZE, &heights_of(&cells), &spawns()),
+                None,
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn stacks_stay_within_the_height_limit() {
+        let generator = MapGenerator {
+            seed: 7,
+            max_height: 3,
+        };
+        let cells = generator.generate(SIZE, &spawns());
+
+        assert!(heights_of(&cells)
+            .iter()
+            .all(|height| (0..=3).contains(height)));
+        assert!(cells.iter().all(|cell| cell
+            .stack
+            .iter()
+            .map(|tile| tile.floor)
+            .eq(0..cell.stack.len() as i32)));
+    }
+
+    #[test]
+    fn the_same_seed_builds_the_same_map() {
+        let generator = MapGenerator {
+            seed: 42,
+            max_height: 4,
+        };
+
+        assert_eq!(
+            heights_of(&generator.generate(SIZE, &spawns())),
+            heights_of(&generator.generate(SIZE, &spawns()))
+        );
+    }
+
+    #[test]
+    fn carving_makes_a_cliff_climbable() {
+        let size = (5, 1);
+        let mut heights = vec![0, 4, 4, 4, 0];
+        let from = Coordinates(0, 0, Side::Center);
+        let to = Coordinates(4, 0, Side::Center);
+        assert_eq!(first_unreachable(size, &heights, &[from, to]), Some(to));
+
+        carve(size, &mut heights, from, to);
+
+        assert_eq!(first_unreachable(size, &heights, &[from, to]), None);
+    }
+}
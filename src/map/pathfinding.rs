@@ -0,0 +1,236 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bracket_lib::prelude::{
+    a_star_search, Algorithm2D, BaseMap, DijkstraMap, DistanceAlg, Point, SmallVec,
+};
+
+use super::{Coordinates, Floor, Map, Side};
+
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// [`Map`] as walked by a unit that can climb or drop at most `jump` floors per step.
+/// Tiles in `occupants` can't be stepped on.
+pub struct Terrain<'a> {
+    pub map: &'a Map,
+    pub jump: Floor,
+    /// Usually every occupant of the map, or only the ones a faction knows about.
+    pub occupants: &'a HashMap<Coordinates, Entity>,
+}
+
+impl<'a> Terrain<'a> {
+    /// The map blocked by all of its occupants.
+    pub fn new(map: &'a Map, jump: Floor) -> Self {
+        Terrain {
+            map,
+            jump,
+            occupants: &map.occupants,
+        }
+    }
+
+    pub fn can_step(&self, from: Coordinates, to: Coordinates) -> bool {
+        if !self.map.in_bounds(to) || self.occupants.contains_key(&to) {
+            return false;
+        }
+
+        match (self.map.surface(from), self.map.surface(to)) {
+            (Some(from), Some(to)) => (to.0 - from.0).abs() <= self.jump.0,
+            _ => false,
+        }
+    }
+
+    pub fn neighbours(&self, coordinates: Coordinates) -> impl Iterator<Item = Coordinates> + '_ {
+        DIRECTIONS
+            .iter()
+            .map(move |(x, y)| Coordinates(coordinates.0 + x, coordinates.1 + y, Side::Center))
+            .filter(move |neighbour| self.can_step(coordinates, *neighbour))
+    }
+}
+
+impl BaseMap for Terrain<'_> {
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let coordinates = self.map.index_to_coordinates(idx);
+
+        self.neighbours(coordinates)
+            .map(|neighbour| (self.map.coordinates_to_index(neighbour), 1.))
+            .collect()
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        DistanceAlg::Manhattan.distance2d(self.index_to_point2d(idx1), self.index_to_point2d(idx2))
+    }
+}
+
+impl Algorithm2D for Terrain<'_> {
+    fn dimensions(&self) -> Point {
+        Point::new(self.map.size.x as i32, self.map.size.y as i32)
+    }
+}
+
+impl Terrain<'_> {
+    /// Steps from `from` to `to`, excluding `from`, walking on the top surface of each stack.
+    pub fn path(&self, from: Coordinates, to: Coordinates) -> Option<Vec<Coordinates>> {
+        let map = self.map;
+        if !map.in_bounds(from) || !map.in_bounds(to) {
+            return None;
+        }
+
+        if from == to {
+            return Some(Vec::new());
+        }
+
+        let path = a_star_search(
+            map.coordinates_to_index(from),
+            map.coordinates_to_index(to),
+            self,
+        );
+
+        if !path.success {
+            return None;
+        }
+
+        let steps = path
+            .steps
+            .into_iter()
+            .skip(1)
+            .map(|idx| map.index_to_coordinates(idx))
+            .collect();
+
+        Some(steps)
+    }
+
+    /// Every tile reachable from `from` in at most `moves` steps, excluding `from`.
+    pub fn reachable(&self, from: Coordinates, moves: i32) -> HashSet<Coordinates> {
+        let map = self.map;
+        if !map.in_bounds(from) {
+            return HashSet::new();
+        }
+
+        let start = map.coordinates_to_index(from);
+        // The search stops short of its depth limit, so it's given one step of slack.
+        let dijkstra = DijkstraMap::new(
+            map.size.x as usize,
+            map.size.y as usize,
+            &[start],
+            self,
+            moves as f32 + 1.,
+        );
+
+        dijkstra
+            .map
+            .iter()
+            .enumerate()
+            .filter(|(idx, distance)| *idx != start && **distance <= moves as f32)
+            .map(|(idx, _)| map.index_to_coordinates(idx))
+            .collect()
+    }
+}
+
+impl Map {
+    /// [`Terrain::path`] around every occupant.
+    pub fn path(
+        &self,
+        from: Coordinates,
+        to: Coordinates,
+        jump: Floor,
+    ) -> Option<Vec<Coordinates>> {
+        Terrain::new(self, jump).path(from, to)
+    }
+
+    /// [`Terrain::reachable`] around every occupant.
+    pub fn reachable(&self, from: Coordinates, moves: i32, jump: Floor) -> HashSet<Coordinates> {
+        Terrain::new(self, jump).reachable(from, moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Coordinates {
+        Coordinates(x, y, Side::Center)
+    }
+
+    #[test]
+    fn path_climbs_within_jump() {
+        let map = Map::from_heights(&[&[0, 1, 2]]);
+
+        assert_eq!(
+            map.path(at(0, 0), at(2, 0), Floor(1)),
+            Some(vec![at(1, 0), at(2, 0)])
+        );
+        assert_eq!(map.path(at(0, 0), at(2, 0), Floor(0)), None);
+    }
+
+    #[test]
+    fn path_limits_drops_like_climbs() {
+        let map = Map::from_heights(&[&[3, 0]]);
+
+        assert_eq!(map.path(at(0, 0), at(1, 0), Floor(2)), None);
+        assert_eq!(map.path(at(0, 0), at(1, 0), Floor(3)), Some(vec![at(1, 0)]));
+    }
+
+    #[test]
+    fn path_goes_around_cliffs_and_occupants() {
+        let mut map = Map::from_heights(&[
+            &[0, 4, 0], //
+            &[0, 0, 0],
+        ]);
+
+        let path = map.path(at(0, 0), at(2, 0), Floor(1)).unwrap();
+        assert_eq!(path, [at(0, 1), at(1, 1), at(2, 1), at(2, 0)]);
+
+        map.occupants.insert(at(1, 1), Entity::from_raw(100));
+        assert_eq!(map.path(at(0, 0), at(2, 0), Floor(1)), None);
+    }
+
+    #[test]
+    fn path_to_itself_is_empty() {
+        let map = Map::from_heights(&[&[0, 0]]);
+
+        assert_eq!(map.path(at(1, 0), at(1, 0), Floor(0)), Some(Vec::new()));
+        assert_eq!(map.path(at(1, 0), at(5, 0), Floor(0)), None);
+    }
+
+    #[test]
+    fn reachable_respects_moves_and_jump() {
+        let map = Map::from_heights(&[
+            &[0, 0, 0, 0], //
+            &[0, 2, 0, 0],
+        ]);
+
+        let reachable = map.reachable(at(0, 0), 2, Floor(1));
+        assert_eq!(
+            reachable,
+            HashSet::from([at(1, 0), at(2, 0), at(0, 1)]),
+            "the raised tile is skipped and the origin left out"
+        );
+
+        let reachable = map.reachable(at(0, 0), 2, Floor(2));
+        assert!(reachable.contains(&at(1, 1)));
+    }
+
+    #[test]
+    fn reachable_skips_only_the_given_occupants() {
+        let map = Map::from_heights(&[&[0, 0, 0]]);
+        let mut occupants = HashMap::new();
+
+        let terrain = Terrain {
+            map: &map,
+            jump: Floor(0),
+            occupants: &occupants,
+        };
+        assert_eq!(
+            terrain.reachable(at(0, 0), 2),
+            HashSet::from([at(1, 0), at(2, 0)])
+        );
+
+        occupants.insert(at(1, 0), Entity::from_raw(100));
+        let terrain = Terrain {
+            map: &map,
+            jump: Floor(0),
+            occupants: &occupants,
+        };
+        assert!(terrain.reachable(at(0, 0), 2).is_empty());
+    }
+}
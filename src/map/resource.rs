@@ -1,17 +1,92 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_4;
 
 use bevy::prelude::*;
 
-use super::{components::Coordinates, Position, Side, MAP_SIZE, SCALE_FACTOR, TILE_SIZE};
+use super::{
+    components::Coordinates, Floor, MapData, Position, Side, MAP_SIZE, SCALE_FACTOR, TILE_SIZE,
+};
 
 const ISOMETRIC_I: Vec2 = Vec2::new(1f32, -0.5f32);
 const ISOMETRIC_J: Vec2 = Vec2::new(-1f32, -0.5f32);
 
+/// The map asset the battle is built from.
+#[derive(Resource)]
+pub struct CurrentMap(pub Handle<MapData>);
+
+/// Texture atlas shared by every tile sprite.
+#[derive(Resource)]
+pub struct Tileset(pub Handle<TextureAtlas>);
+
+/// Texture atlas of the map indicators used by every [`Cursor`](super::Cursor).
+#[derive(Resource)]
+pub struct Indicators(pub Handle<TextureAtlas>);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Highlight {
+    Move,
+    Attack,
+}
+
+/// Tiles marked with a [`RangeCursor`](super::RangeCursor) or an [`AttackCursor`](super::AttackCursor).
+#[derive(Resource, Default)]
+pub struct Highlights(pub HashMap<Coordinates, Highlight>);
+
+/// What last moved the [`HoverCursor`](super::HoverCursor).
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Pointing {
+    #[default]
+    Mouse,
+    Buttons,
+}
+
+/// The tile under the [`HoverCursor`](super::HoverCursor).
+#[derive(Resource, Default, Debug)]
+pub struct TileCursor {
+    pub pointing: Pointing,
+    pub coordinates: Coordinates,
+}
+
+/// Which way the battlefield is turned on screen, in quarter turns clockwise from `North`.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Orientation {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Orientation {
+    pub fn clockwise(&self) -> Orientation {
+        match self {
+            Orientation::North => Orientation::East,
+            Orientation::East => Orientation::South,
+            Orientation::South => Orientation::West,
+            Orientation::West => Orientation::North,
+        }
+    }
+
+    pub fn counterclockwise(&self) -> Orientation {
+        match self {
+            Orientation::North => Orientation::West,
+            Orientation::East => Orientation::North,
+            Orientation::South => Orientation::East,
+            Orientation::West => Orientation::South,
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct Map {
     pub size: Vec2,
     pub tile_size: Vec2,
+    pub orientation: Orientation,
     pub tiles: HashMap<Coordinates, Vec<Entity>>,
+    pub occupants: HashMap<Coordinates, Entity>,
+    /// Positions of the tiles stacked on each cell, lowest first.
+    stacks: HashMap<Coordinates, Vec<Position>>,
     half_size: Vec2,
     half_tile_size: Vec2,
 }
@@ -25,26 +100,102 @@ impl Default for Map {
 impl Map {
     pub fn new(size: Vec2, tile_size: Vec2, scale_factor: f32) -> Self {
         let tiles = HashMap::new();
+        let occupants = HashMap::new();
+        let stacks = HashMap::new();
         let tile_size = tile_size * scale_factor;
         let half_tile_size = tile_size / 2.0;
         let half_size = size / 2.0;
 
         Self {
             size,
+            orientation: Orientation::default(),
             half_size,
             tiles,
+            occupants,
+            stacks,
             tile_size,
             half_tile_size,
         }
     }
 
+    pub fn insert_tile(&mut self, position: &Position, entity: Entity) {
+        let coordinates = position.coordinates;
+        self.tiles.entry(coordinates).or_default().push(entity);
+
+        let stack = self.stacks.entry(coordinates).or_default();
+        let index = stack.partition_point(|tile| tile.floor <= position.floor);
+        stack.insert(index, *position);
+    }
+
+    /// Floor of the topmost tile stacked on `coordinates`.
+    pub fn surface(&self, coordinates: Coordinates) -> Option<Floor> {
+        self.stacks
+            .get(&coordinates)
+            .and_then(|stack| stack.last())
+            .map(|position| position.floor)
+    }
+
     pub fn index_to_coordinates(&self, index: usize) -> Coordinates {
         let x = (index as f32 % self.size.x) as i32;
         let y = (index as f32 / self.size.x).floor() as i32;
         Coordinates(x, y, Side::Center)
     }
 
+    pub fn coordinates_to_index(&self, coordinates: Coordinates) -> usize {
+        (coordinates.1 * self.size.x as i32 + coordinates.0) as usize
+    }
+
+    /// Where `coordinates` are drawn in the current orientation, as coordinates of the
+    /// unturned grid.
+    fn coordinates_to_view(&self, coordinates: Coordinates) -> IVec2 {
+        let (x, y) = (coordinates.0, coordinates.1);
+        let (last_x, last_y) = (self.size.x as i32 - 1, self.size.y as i32 - 1);
+
+        match self.orientation {
+            Orientation::North => IVec2::new(x, y),
+            Orientation::East => IVec2::new(last_y - y, x),
+            Orientation::South => IVec2::new(last_x - x, last_y - y),
+            Orientation::West => IVec2::new(y, last_x - x),
+        }
+    }
+
+    fn view_to_coordinates(&self, view: IVec2, side: Side) -> Coordinates {
+        let (last_x, last_y) = (self.size.x as i32 - 1, self.size.y as i32 - 1);
+
+        let (x, y) = match self.orientation {
+            Orientation::North => (view.x, view.y),
+            Orientation::East => (view.y, last_y - view.x),
+            Orientation::South => (last_x - view.x, last_y - view.y),
+            Orientation::West => (last_x - view.y, view.x),
+        };
+        Coordinates(x, y, side)
+    }
+
+    fn view_half_size(&self) -> Vec2 {
+        match self.orientation {
+            Orientation::North | Orientation::South => self.half_size,
+            Orientation::East | Orientation::West => self.half_size.yx(),
+        }
+    }
+
     pub fn point_to_coordinates(&self, point: Vec2) -> Coordinates {
+        let view = self.point_to_view(point);
+        let coordinate_x_i32 = view.x.ceil() as i32;
+        let coordinate_y_i32 = view.y.ceil() as i32;
+
+        let diff = coordinate_x_i32 as f32 - view.x;
+
+        let side = match diff {
+            0.0..=0.5 => Side::Right,
+            _ => Side::Left,
+        };
+
+        self.view_to_coordinates(IVec2::new(coordinate_x_i32, coordinate_y_i32), side)
+    }
+
+    /// Where `point` falls on the floor 0 plane, in unrounded view coordinates: cell `(x, y)`
+    /// covers everything above `x - 1` up to `x`, and likewise for `y`.
+    fn point_to_view(&self, point: Vec2) -> Vec2 {
         let x = point.x;
         let y = point.y;
 
@@ -60,56 +211,137 @@ impl Map {
         let inv_c = det * -c;
         let inv_d = det * a;
 
-        let coordinate_x = (x * inv_a + y * inv_b) + self.half_size.x;
-        let coordinate_y = (x * inv_c + y * inv_d) + self.half_size.y;
-        let coordinate_x_i32 = coordinate_x.ceil() as i32;
-        let coordinate_y_i32 = coordinate_y.ceil() as i32;
-        info!("x: {coordinate_x} | x_i32: ${coordinate_x_i32} | y: {coordinate_y} y_i32: {coordinate_y_i32}");
+        // Undoes the y offset of `view_to_point`, which moves both axes alike.
+        let offset = self.view_half_size().y;
+        let coordinate_x = (x * inv_a + y * inv_b) + offset;
+        let coordinate_y = (x * inv_c + y * inv_d) + offset;
 
-        let diff = coordinate_x_i32 as f32 - coordinate_x;
+        Vec2::new(coordinate_x, coordinate_y)
+    }
 
-        let side = match diff {
-            0.0..=0.5 => Side::Right,
-            _ => Side::Left
-        };
+    /// The topmost tile drawn under `point`, top face or side, for stacks of any height.
+    ///
+    /// Each floor up draws a tile a cell back on both axes, so the tiles that could be under
+    /// `point` lie along a diagonal through the map. That diagonal is walked front to back,
+    /// and the first tile it passes through wins. The returned side is `Center` for a top
+    /// face, or the face of the tile that was hit.
+    pub fn pick(&self, point: Vec2) -> Option<Position> {
+        let point = self.point_to_view(point);
+        let last = self.view_half_size() * 2. - 1.;
+
+        // How far down, in floors, the point is looked through from the front of the map.
+        let depth = (last - point).min_element();
+        let mut column = (point + depth).ceil().as_ivec2();
 
-        Coordinates(coordinate_x_i32, coordinate_y_i32, side)
+        while column.x >= 0 && column.y >= 0 {
+            // The span of heights, in floors, at which the diagonal is over this column.
+            let near = column.as_vec2() - point;
+            let far = near - 1.;
+            let (top, bottom) = (near.min_element(), far.max_element());
+
+            let coordinates = self.view_to_coordinates(column, Side::Center);
+            let hit = self.stacks.get(&coordinates).and_then(|stack| {
+                stack.iter().rev().find(|tile| {
+                    let floor = tile.floor.0 as f32;
+                    floor > bottom && floor - 1. < top
+                })
+            });
+
+            if let Some(tile) = hit {
+                let side = if tile.floor.0 as f32 <= top {
+                    Side::Center
+                } else if near.x < near.y {
+                    Side::Right
+                } else {
+                    Side::Left
+                };
+
+                return Some(Position {
+                    coordinates: Coordinates(tile.coordinates.0, tile.coordinates.1, side),
+                    ..*tile
+                });
+            }
+
+            column -= match far.x.total_cmp(&far.y) {
+                Ordering::Greater => IVec2::X,
+                Ordering::Less => IVec2::Y,
+                Ordering::Equal => IVec2::ONE,
+            };
+        }
+
+        None
     }
 
     pub fn coordinates_to_point(&self, coordinates: Coordinates) -> Vec2 {
-        let x = coordinates.0 as f32;
-        let y = coordinates.1 as f32;
+        self.view_to_point(self.coordinates_to_view(coordinates))
+    }
+
+    fn view_to_point(&self, view: IVec2) -> Vec2 {
+        let x = view.x as f32;
+        let y = view.y as f32;
 
         let a = ISOMETRIC_I.x * x * self.half_tile_size.x;
         let b = ISOMETRIC_J.x * y * self.half_tile_size.x;
         let c = ISOMETRIC_I.y * x * self.half_tile_size.y;
         let d = ISOMETRIC_J.y * y * self.half_tile_size.y;
 
-        let y_offset = self.half_size.y * self.half_tile_size.y;
+        let y_offset = self.view_half_size().y * self.half_tile_size.y;
 
         Vec2::new(a + b, c + d + y_offset)
     }
 
     pub fn position_to_translation(&self, position: &Position) -> Vec3 {
-        let coordinates = position.coordinates - position.floor;
-        let point = self.coordinates_to_point(coordinates);
-        let z = position.order.0 / 5.
-            + (coordinates.1 + coordinates.0) as f32 / 20.
-            + position.floor.0 as f32 / 2.;
+        // Stacked tiles are drawn a cell up and to the back on screen per floor.
+        let view = self.coordinates_to_view(position.coordinates) - IVec2::splat(position.floor.0);
+        let point = self.view_to_point(view);
+        let z =
+            position.order.0 / 5. + (view.y + view.x) as f32 / 20. + position.floor.0 as f32 / 2.;
         Vec3::from((point, z))
     }
 
     pub fn position_to_translation_cursor(&self, position: &Position) -> Vec3 {
-        let coordinates = position.coordinates - position.floor;
-        let point = self.coordinates_to_point(coordinates);
+        let view = self.coordinates_to_view(position.coordinates) - IVec2::splat(position.floor.0);
+        let point = self.view_to_point(view);
         let bla = self.half_tile_size.x * position.floor.0 as f32;
         let point = Vec2::new(point.x + bla, point.y);
-        let z = position.order.0 / 5.
-            + (coordinates.1 + coordinates.0) as f32 / 20.
-            + position.floor.0 as f32 / 2.;
+        let z =
+            position.order.0 / 5. + (view.y + view.x) as f32 / 20. + position.floor.0 as f32 / 2.;
         Vec3::from((point, z))
     }
 
+    /// The step to a neighbouring cell that heads closest to `direction` on screen. The
+    /// direction is turned an eighth clockwise first, so that up, right, down and left each
+    /// get a diagonal of their own.
+    pub fn screen_step(&self, direction: Vec2) -> IVec2 {
+        let direction = Vec2::from_angle(-FRAC_PI_4).rotate(direction);
+        let origin = self.coordinates_to_point(Coordinates(0, 0, Side::Center));
+        let alignment = |step: &IVec2| {
+            let point = self.coordinates_to_point(Coordinates(step.x, step.y, Side::Center));
+            (point - origin).normalize_or_zero().dot(direction)
+        };
+
+        [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+            .into_iter()
+            .max_by(|a, b| alignment(a).total_cmp(&alignment(b)))
+            .unwrap_or_default()
+    }
+
+    /// The area the map's tiles are drawn in, with a tile of margin for stacks and sprites.
+    pub fn bounds(&self) -> Rect {
+        let (width, height) = (self.size.x as i32 - 1, self.size.y as i32 - 1);
+        let corners = [(0, 0), (width, 0), (0, height), (width, height)]
+            .map(|(x, y)| self.coordinates_to_point(Coordinates(x, y, Side::Center)));
+
+        let min = corners
+            .iter()
+            .fold(Vec2::MAX, |min, corner| min.min(*corner));
+        let max = corners
+            .iter()
+            .fold(Vec2::MIN, |max, corner| max.max(*corner));
+
+        Rect::from_corners(min, max).inset(self.tile_size.max_element())
+    }
+
     pub fn in_bounds(&self, coordinates: Coordinates) -> bool {
         let x = coordinates.0 as f32;
         let y = coordinates.1 as f32;
@@ -117,3 +349,117 @@ impl Map {
         x >= 0. && x < self.size.x && y >= 0. && y < self.size.y
     }
 }
+
+#[cfg(test)]
+impl Map {
+    /// A map with a stack of tiles on every cell, `heights[y][x]` floors above the ground.
+    pub fn from_heights(heights: &[&[i32]]) -> Map {
+        let size = Vec2::new(heights[0].len() as f32, heights.len() as f32);
+        let mut map = Map::new(size, TILE_SIZE, SCALE_FACTOR);
+        let mut tiles = 0;
+
+        for (y, row) in heights.iter().enumerate() {
+            for (x, height) in row.iter().enumerate() {
+                for floor in 0..=*height {
+                    let position = Position {
+                        coordinates: Coordinates(x as i32, y as i32, Side::Center),
+                        floor: Floor(floor),
+                        ..default()
+                    };
+                    map.insert_tile(&position, Entity::from_raw(tiles));
+                    tiles += 1;
+                }
+            }
+        }
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIENTATIONS: [Orientation; 4] = [
+        Orientation::North,
+        Orientation::East,
+        Orientation::South,
+        Orientation::West,
+    ];
+
+    fn top(map: &Map, x: i32, y: i32) -> Position {
+        Position {
+            coordinates: Coordinates(x, y, Side::Center),
+            floor: map.surface(Coordinates(x, y, Side::Center)).unwrap(),
+            ..default()
+        }
+    }
+
+    /// The middle of the top face of the tile at `position`.
+    fn face(map: &Map, position: &Position) -> Vec2 {
+        map.position_to_translation(position).truncate() + Vec2::new(0., map.half_tile_size.y / 2.)
+    }
+
+    fn picked(map: &Map, point: Vec2) -> Option<(Coordinates, Floor)> {
+        map.pick(point)
+            .map(|position| (position.coordinates, position.floor))
+    }
+
+    #[test]
+    fn views_round_trip_in_every_orientation() {
+        let mut map = Map::from_heights(&[&[0, 0, 0, 0], &[0, 0, 0, 0], &[0, 0, 0, 0]]);
+
+        for orientation in ORIENTATIONS {
+            map.orientation = orientation;
+            let last = (map.view_half_size() * 2.).as_ivec2() - 1;
+
+            for (x, y) in (0..3).flat_map(|y| (0..4).map(move |x| (x, y))) {
+                let coordinates = Coordinates(x, y, Side::Center);
+                let view = map.coordinates_to_view(coordinates);
+
+                assert!(view.cmpge(IVec2::ZERO).all() && view.cmple(last).all());
+                assert_eq!(map.view_to_coordinates(view, Side::Center), coordinates);
+            }
+        }
+    }
+
+    #[test]
+    fn pick_finds_every_top_face_in_every_orientation() {
+        let mut map = Map::from_heights(&[&[1, 1, 1, 1], &[1, 1, 1, 1], &[1, 1, 1, 1]]);
+
+        for orientation in ORIENTATIONS {
+            map.orientation = orientation;
+
+            for (x, y) in (0..3).flat_map(|y| (0..4).map(move |x| (x, y))) {
+                let position = top(&map, x, y);
+
+                assert_eq!(
+                    picked(&map, face(&map, &position)),
+                    Some((position.coordinates, position.floor)),
+                    "{orientation:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pick_prefers_the_stack_in_front() {
+        let map = Map::from_heights(&[&[0, 0, 0], &[0, 2, 0], &[0, 0, 0]]);
+
+        let pillar = top(&map, 1, 1);
+        assert_eq!(
+            picked(&map, face(&map, &pillar)),
+            Some((pillar.coordinates, Floor(2)))
+        );
+
+        // The pillar's side covers the ground just behind it.
+        let behind = top(&map, 0, 0);
+        assert_eq!(
+            picked(&map, face(&map, &behind)),
+            Some((Coordinates(1, 1, Side::Left), Floor(2)))
+        );
+
+        // Nothing is drawn far above the map.
+        assert_eq!(picked(&map, face(&map, &behind) + Vec2::new(0., 80.)), None);
+    }
+}
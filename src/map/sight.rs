@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use bracket_lib::prelude::{line2d, DistanceAlg, LineAlg, Point};
+
+use super::{Coordinates, Map, Position, Side, EYE_HEIGHT};
+
+impl Map {
+    /// Whether a unit standing at `from` can see the unit or tile surface at `to`.
+    /// The sight line runs from eye height to eye height, and any stack rising above it
+    /// on the cells in between blocks it.
+    pub fn line_of_sight(&self, from: &Position, to: Coordinates) -> bool {
+        if !self.in_bounds(from.coordinates) || !self.in_bounds(to) {
+            return false;
+        }
+
+        let Some(target_floor) = self.surface(to) else {
+            return false;
+        };
+
+        let start = Point::new(from.coordinates.0, from.coordinates.1);
+        let end = Point::new(to.0, to.1);
+        let eye = from.floor.0 as f32 + EYE_HEIGHT;
+        let target = target_floor.0 as f32 + EYE_HEIGHT;
+        let length = DistanceAlg::Pythagoras.distance2d(start, end);
+
+        line2d(LineAlg::Bresenham, start, end)
+            .into_iter()
+            .filter(|point| *point != start && *point != end)
+            .all(|point| {
+                let coordinates = Coordinates(point.x, point.y, Side::Center);
+                let travelled = DistanceAlg::Pythagoras.distance2d(start, point) / length;
+                let height = eye + (target - eye) * travelled;
+
+                match self.surface(coordinates) {
+                    Some(floor) => (floor.0 as f32) <= height,
+                    None => true,
+                }
+            })
+    }
+
+    /// Every tile within `range` of `viewer` that it has a line of sight to, its own included.
+    pub fn field_of_view(&self, viewer: &Position, range: i32) -> HashSet<Coordinates> {
+        let origin = viewer.coordinates;
+        let offsets = -range..=range;
+
+        offsets
+            .clone()
+            .flat_map(|x| offsets.clone().map(move |y| (x, y)))
+            .filter(|(x, y)| x * x + y * y <= range * range)
+            .map(|(x, y)| Coordinates(origin.0 + x, origin.1 + y, Side::Center))
+            .filter(|to| self.in_bounds(*to))
+            .filter(|to| *to == origin || self.line_of_sight(viewer, *to))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Floor;
+
+    fn standing(x: i32, y: i32, floor: i32) -> Position {
+        Position {
+            coordinates: Coordinates(x, y, Side::Center),
+            floor: Floor(floor),
+            ..Default::default()
+        }
+    }
+
+    fn at(x: i32, y: i32) -> Coordinates {
+        Coordinates(x, y, Side::Center)
+    }
+
+    #[test]
+    fn flat_ground_never_blocks() {
+        let map = Map::from_heights(&[&[0, 0, 0, 0, 0]]);
+
+        assert!(map.line_of_sight(&standing(0, 0, 0), at(4, 0)));
+    }
+
+    #[test]
+    fn walls_above_eye_height_block() {
+        let map = Map::from_heights(&[&[0, 0, 1, 0, 0]]);
+
+        assert!(!map.line_of_sight(&standing(0, 0, 0), at(4, 0)));
+    }
+
+    #[test]
+    fn height_sees_over_walls() {
+        let map = Map::from_heights(&[&[2, 0, 1, 0, 0]]);
+
+        assert!(map.line_of_sight(&standing(0, 0, 2), at(4, 0)));
+    }
+
+    #[test]
+    fn sight_is_symmetric_between_surfaces() {
+        let map = Map::from_heights(&[&[3, 0, 2, 0, 0]]);
+
+        let up = map.line_of_sight(&standing(4, 0, 0), at(0, 0));
+        let down = map.line_of_sight(&standing(0, 0, 3), at(4, 0));
+        assert_eq!(up, down);
+    }
+
+    #[test]
+    fn nothing_is_seen_off_the_map() {
+        let map = Map::from_heights(&[&[0, 0]]);
+
+        assert!(!map.line_of_sight(&standing(0, 0, 0), at(2, 0)));
+        assert!(!map.line_of_sight(&standing(-1, 0, 0), at(1, 0)));
+    }
+
+    #[test]
+    fn field_of_view_includes_the_viewer_and_stops_at_walls() {
+        let map = Map::from_heights(&[&[0, 0, 1, 0]]);
+
+        let seen = map.field_of_view(&standing(0, 0, 0), 3);
+        assert_eq!(seen, HashSet::from([at(0, 0), at(1, 0), at(2, 0)]));
+    }
+}
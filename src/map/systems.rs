@@ -1,160 +1,138 @@
-use bevy::{prelude::*, sprite::Anchor};
+use bevy::{asset::RecursiveDependencyLoadState, prelude::*, sprite::Anchor};
 
-use super::{components::{Floor, Order}, resource::Map, Coordinates, Position, TileBundle, SCALE_FACTOR, TILE_SIZE, Side};
+use crate::rng::{Rng, Stream};
+use crate::save::SavedBattle;
+use crate::state::{DespawnOnExit, GameState};
+use crate::unit::Unit;
+
+use super::{
+    components::Order, resource::Map, CurrentMap, Highlights, MapData, MapLoaded, Position,
+    SpawnData, Tile, TileBundle, TileCursor, Tileset, SCALE_FACTOR, TILE_SIZE,
+};
+
+type DrawnQuery<'w, 's> =
+    Query<'w, 's, (&'static mut Transform, &'static Position), Or<(With<Tile>, With<Unit>)>>;
 
 pub fn setup(
     mut commands: Commands,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     asset_server: Res<AssetServer>,
-    mut map: ResMut<Map>,
 ) {
     let columns = 11;
     let rows = 10;
     let texture_handle = asset_server.load("textures/Isometric_MedievalFantasy_Tiles.png");
     let texture_atlas =
         TextureAtlas::from_grid(texture_handle.clone(), TILE_SIZE, columns, rows, None, None);
-    let texture_atlas_handle = &texture_atlases.add(texture_atlas);
-
-    let tiles_count = (map.size.x * map.size.y) as usize;
-
-    for i in 0..tiles_count {
-        let coordinates = map.index_to_coordinates(i);
-        let position = Position {
-            coordinates,
-            floor: Floor(0),
-            order: Order(0.),
-        };
-        let translation = map.position_to_translation(&position);
-
-        let mut sprite = TextureAtlasSprite::new(92);
-        sprite.anchor = Anchor::Center;
-
-        let entity = commands.spawn(TileBundle {
-            sprite: SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle.clone(),
-                sprite,
-                transform: Transform {
-                    translation,
-                    scale: Vec3::splat(SCALE_FACTOR),
-                    ..default()
-                },
-                ..default()
-            },
-            position,
-        });
-
-        match map.tiles.get_mut(&coordinates) {
-            Some(entities) => entities.push(entity.id()),
-            None => {
-                map.tiles.insert(coordinates, vec![entity.id()]);
-            }
-        }
-    }
 
-    let coordinates = Coordinates(10, 10, Side::Center);
-
-    for i in 1..=2 {
-        let position = Position {
-            coordinates,
-            floor: Floor(i),
-            order: Order(0.),
-        };
-        let translation = map.position_to_translation(&position);
-
-        let entity = commands.spawn(TileBundle {
-            sprite: SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(93),
-                transform: Transform {
-                    scale: Vec3::splat(SCALE_FACTOR),
-                    translation,
-                    ..default()
-                },
-                ..default()
-            },
-            position,
-        });
-
-        match map.tiles.get_mut(&coordinates) {
-            Some(entities) => entities.push(entity.id()),
-            None => {
-                map.tiles.insert(coordinates, vec![entity.id()]);
-            }
-        }
+    commands.insert_resource(Tileset(texture_atlases.add(texture_atlas)));
+}
+
+/// Builds the current map once it's loaded, with a fresh layout if it's generated.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_map(
+    mut commands: Commands,
+    mut map_loaded: EventWriter<MapLoaded>,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<MapData>>,
+    current_map: Res<CurrentMap>,
+    tileset: Res<Tileset>,
+    saved: Option<Res<SavedBattle>>,
+    mut rng: ResMut<Rng>,
+    mut map: ResMut<Map>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !map.tiles.is_empty() {
+        return;
     }
 
-    let coordinates = Coordinates(9, 10, Side::Center);
-
-    for i in 1..2 {
-        let position = Position {
-            coordinates,
-            floor: Floor(i),
-            order: Order(0.),
-        };
-        let translation = map.position_to_translation(&position);
-
-        let entity = commands.spawn(TileBundle {
-            sprite: SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(93),
-                transform: Transform {
-                    scale: Vec3::splat(SCALE_FACTOR),
-                    translation,
-                    ..default()
-                },
-                ..default()
-            },
-            position,
-        });
-
-        match map.tiles.get_mut(&coordinates) {
-            Some(entities) => entities.push(entity.id()),
-            None => {
-                map.tiles.insert(coordinates, vec![entity.id()]);
-            }
+    match asset_server.recursive_dependency_load_state(&current_map.0) {
+        RecursiveDependencyLoadState::Loaded => {}
+        RecursiveDependencyLoadState::Failed => {
+            error!("could not load map {:?}", current_map.0.path());
+            next_state.set(GameState::MainMenu);
+            return;
         }
+        _ => return,
     }
 
-    for i in 0..10 {
-        let position = Position {
-            coordinates: Coordinates(i, 0, Side::Center),
-            floor: Floor(1),
-            order: Order(0.),
-        };
-        let translation = map.position_to_translation(&position);
-
-        let entity = commands.spawn(TileBundle {
-            sprite: SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle.clone(),
-                sprite: TextureAtlasSprite::new(93),
-                transform: Transform {
-                    scale: Vec3::splat(SCALE_FACTOR),
-                    translation,
-                    ..default()
+    let Some(data) = maps.get(&current_map.0) else {
+        return;
+    };
+
+    // A saved battle is rebuilt as it was instead of from the map asset.
+    let generated;
+    let (size, cells) = match (&saved, data.generator) {
+        (Some(saved), _) => (saved.0.size, &saved.0.cells),
+        (None, Some(mut generator)) => {
+            generator.seed = rng.stream(Stream::Map).next_u64();
+            let spawns: Vec<_> = data.spawns.iter().map(SpawnData::coordinates).collect();
+            generated = generator.generate(data.size, &spawns);
+            (data.size, &generated)
+        }
+        (None, None) => (data.size, &data.cells),
+    };
+
+    let size = Vec2::new(size.0 as f32, size.1 as f32);
+    *map = Map::new(size, TILE_SIZE, SCALE_FACTOR);
+
+    for cell in cells {
+        let coordinates = cell.coordinates();
+
+        for tile in &cell.stack {
+            let position = Position {
+                coordinates,
+                floor: tile.floor(),
+                order: Order(0.),
+            };
+            let translation = map.position_to_translation(&position);
+
+            let mut sprite = TextureAtlasSprite::new(tile.index);
+            sprite.anchor = Anchor::Center;
+
+            let entity = commands.spawn((
+                TileBundle {
+                    sprite: SpriteSheetBundle {
+                        texture_atlas: tileset.0.clone(),
+                        sprite,
+                        transform: Transform {
+                            translation,
+                            scale: Vec3::splat(SCALE_FACTOR),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    position,
+                    tile: Tile,
                 },
-                ..default()
-            },
-            position,
-        });
-
-        if let Some(entities) = map.tiles.get_mut(&position.coordinates) {
-            info!("coordinates: {:?}", position.coordinates);
-            info!("entities length: {:?}", entities.len());
-            entities.push(entity.id())
+                DespawnOnExit(GameState::Battle),
+            ));
+
+            map.insert_tile(&position, entity.id());
         }
     }
 
-    let entities = map.tiles.get(&Coordinates(0, 0, Side::Center)).unwrap();
-    info!("entities length: {:?}", entities.len());
-    entities.iter().for_each(|entity| {
-        info!("entity id: {:?}", entity);
-    });
+    map_loaded.send(MapLoaded);
+}
+
+pub fn reset_map(mut map: ResMut<Map>, mut highlights: ResMut<Highlights>) {
+    *map = Map::default();
+    highlights.0.clear();
 }
 
-pub fn update_z_index(map: Res<Map>, mut query: Query<(&mut Transform, &Position)>) {
+/// Redraws tiles and units for the new orientation. The cursors follow on their own once
+/// their resources look changed.
+pub fn reorient(
+    map: Res<Map>,
+    mut highlights: ResMut<Highlights>,
+    mut tile_cursor: ResMut<TileCursor>,
+    mut query: DrawnQuery,
+) {
     query.iter_mut().for_each(|(mut transform, position)| {
         transform.translation = map.position_to_translation(position);
     });
+
+    highlights.set_changed();
+    tile_cursor.set_changed();
 }
 
 // pub fn update_position(
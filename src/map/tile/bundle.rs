@@ -2,8 +2,11 @@ use bevy::prelude::*;
 
 use crate::map::Position;
 
+use super::components::Tile;
+
 #[derive(Bundle, Default)]
 pub struct TileBundle {
     pub sprite: SpriteSheetBundle,
     pub position: Position,
+    pub tile: Tile,
 }
@@ -1 +1,4 @@
+use bevy::prelude::*;
 
+#[derive(Component, Copy, Clone, Default)]
+pub struct Tile;
@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use bracket_lib::prelude::RandomNumberGenerator;
+
+mod resource;
+
+pub use resource::*;
+
+pub struct RngPlugin {
+    /// Seed of the run; a random one is picked when `None`.
+    pub seed: Option<u64>,
+}
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = self
+            .seed
+            .unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+
+        info!("run seed: {}", seed);
+
+        app.insert_resource(Rng::new(seed))
+            .register_type::<Rng>()
+            .register_type::<Stream>()
+            .register_type::<StreamRng>()
+            .register_type::<HashMap<Stream, StreamRng>>();
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+/// Independent sequences of random numbers, so drawing from one never shifts another.
+#[derive(Reflect, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Stream {
+    Map,
+    Combat,
+    Run,
+    Loot,
+}
+
+/// Every random number of a run, derived from a single seed. Streams are only seeded once
+/// they're first drawn from, so the default is simply a run with seed 0.
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Default)]
+#[reflect(Resource)]
+pub struct Rng {
+    seed: u64,
+    streams: HashMap<Stream, StreamRng>,
+}
+
+/// The generator of one stream, which shows up only by name in the inspector.
+#[derive(Reflect, Serialize, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct StreamRng(#[reflect(ignore, default = "unseeded")] RandomNumberGenerator);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn stream(&mut self, stream: Stream) -> &mut RandomNumberGenerator {
+        let seed = stream_seed(self.seed, stream);
+
+        &mut self
+            .streams
+            .entry(stream)
+            .or_insert_with(|| StreamRng(RandomNumberGenerator::seeded(seed)))
+            .0
+    }
+}
+
+/// Mixes the run seed with the stream so every stream starts somewhere else (SplitMix64).
+fn stream_seed(seed: u64, stream: Stream) -> u64 {
+    let mut z = seed.wrapping_add((stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Only stands in for a generator rebuilt through reflection; real ones come from [`Rng::stream`].
+fn unseeded() -> RandomNumberGenerator {
+    RandomNumberGenerator::seeded(0)
+}
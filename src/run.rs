@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::combat::Defeated;
+use crate::state::GameState;
+use crate::unit::{Modifier, Stat};
+
+mod components;
+mod graph;
+mod resource;
+mod systems;
+
+pub use components::*;
+pub use graph::*;
+pub use resource::*;
+
+/// Rows of nodes before the boss.
+const RUN_DEPTH: usize = 6;
+
+/// Chance, in percent, of a node also leading to the next lane over.
+const BRANCH_CHANCE: i32 = 40;
+
+const STARTING_PARTY: [&str; 3] = [
+    "units/soldier.unit.ron",
+    "units/archer.unit.ron",
+    "units/scout.unit.ron",
+];
+
+/// Map of ordinary and elite fights, laid out anew from the run seed every time.
+const BATTLE_MAP: &str = "maps/generated.map.ron";
+
+/// The boss is fought on the hand-made map.
+const BOSS_MAP: &str = "maps/battle.map.ron";
+
+const GOLD_REWARD: (i32, i32) = (10, 25);
+
+const SHOP_PRICE: i32 = 20;
+
+/// HP restored at a rest site, in percent of max HP.
+const REST_HEAL: i32 = 30;
+
+const ELITE_MODIFIERS: [Modifier; 2] = [
+    Modifier {
+        stat: Stat::Attack,
+        amount: 2,
+    },
+    Modifier {
+        stat: Stat::Defense,
+        amount: 2,
+    },
+];
+
+const BOSS_MODIFIERS: [Modifier; 3] = [
+    Modifier {
+        stat: Stat::Attack,
+        amount: 4,
+    },
+    Modifier {
+        stat: Stat::Defense,
+        amount: 4,
+    },
+    Modifier {
+        stat: Stat::Evasion,
+        amount: 5,
+    },
+];
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Run>()
+            .init_resource::<Party>()
+            .init_resource::<Encounter>()
+            .add_systems(OnExit(GameState::MainMenu), systems::start_run)
+            .add_systems(OnEnter(GameState::RunMap), systems::show_run_map)
+            .add_systems(
+                Update,
+                (
+                    systems::choose_node.run_if(in_state(GameState::RunMap)),
+                    systems::update_run_map.run_if(
+                        in_state(GameState::RunMap).and_then(
+                            resource_changed::<Run>().or_else(resource_changed::<Party>()),
+                        ),
+                    ),
+                    systems::check_outcome
+                        .run_if(in_state(GameState::Battle).and_then(on_event::<Defeated>())),
+                ),
+            );
+    }
+}
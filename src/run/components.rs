@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+
+/// Index in the [`Party`](super::Party) of the member a unit was spawned from.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct PartyMember(pub usize);
+
+/// Text describing the run between battles.
+#[derive(Component)]
+pub struct RunMapScreen;
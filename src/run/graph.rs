@@ -0,0 +1,165 @@
+use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+use super::{BRANCH_CHANCE, RUN_DEPTH};
+
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum NodeKind {
+    Battle,
+    Elite,
+    Shop,
+    Rest,
+    Event,
+    Boss,
+}
+
+impl NodeKind {
+    pub fn is_battle(&self) -> bool {
+        matches!(self, NodeKind::Battle | NodeKind::Elite | NodeKind::Boss)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunNode {
+    pub kind: NodeKind,
+    /// Row of the node; the run starts on row 0 and ends on the boss.
+    pub depth: usize,
+    /// Nodes of the next row reachable from this one.
+    pub next: Vec<usize>,
+}
+
+/// Rows of nodes linked to the row after them, converging on a single boss.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct RunGraph {
+    pub nodes: Vec<RunNode>,
+}
+
+impl RunGraph {
+    pub fn generate(rng: &mut RandomNumberGenerator) -> Self {
+        let mut rows: Vec<Vec<usize>> = Vec::new();
+        let mut nodes = Vec::new();
+
+        for depth in 0..=RUN_DEPTH {
+            let width = if depth == RUN_DEPTH {
+                1
+            } else {
+                rng.range(2, 4)
+            };
+
+            let row = (0..width)
+                .map(|_| {
+                    nodes.push(RunNode {
+                        kind: node_kind(rng, depth),
+                        depth,
+                        next: Vec::new(),
+                    });
+                    nodes.len() - 1
+                })
+                .collect();
+            rows.push(row);
+        }
+
+        for pair in rows.windows(2) {
+            let (row, next_row) = (&pair[0], &pair[1]);
+
+            // Each node leads to the node below it and sometimes to its neighbour as well.
+            for (lane, node) in row.iter().enumerate() {
+                let target = lane * next_row.len() / row.len();
+                nodes[*node].next.push(next_row[target]);
+
+                if target + 1 < next_row.len() && rng.range(0, 100) < BRANCH_CHANCE {
+                    nodes[*node].next.push(next_row[target + 1]);
+                }
+            }
+
+            // Nodes nothing leads to are linked from the closest lane above.
+            for (lane, next_node) in next_row.iter().enumerate() {
+                if row.iter().any(|node| nodes[*node].next.contains(next_node)) {
+                    continue;
+                }
+
+                let source = (lane * row.len() / next_row.len()).min(row.len() - 1);
+                nodes[row[source]].next.push(*next_node);
+            }
+        }
+
+        Self { nodes }
+    }
+
+    /// Nodes a run can start from.
+    pub fn starts(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|node| self.nodes[*node].depth == 0)
+            .collect()
+    }
+}
+
+fn node_kind(rng: &mut RandomNumberGenerator, depth: usize) -> NodeKind {
+    if depth == 0 {
+        return NodeKind::Battle;
+    }
+
+    if depth == RUN_DEPTH {
+        return NodeKind::Boss;
+    }
+
+    if depth == RUN_DEPTH - 1 {
+        return NodeKind::Rest;
+    }
+
+    match rng.range(0, 100) {
+        0..=44 => NodeKind::Battle,
+        45..=59 => NodeKind::Elite,
+        60..=71 => NodeKind::Shop,
+        72..=83 => NodeKind::Rest,
+        _ => NodeKind::Event,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graphs() -> impl Iterator<Item = RunGraph> {
+        (0..50).map(|seed| RunGraph::generate(&mut RandomNumberGenerator::seeded(seed)))
+    }
+
+    #[test]
+    fn every_node_leads_to_the_boss() {
+        for graph in graphs() {
+            let bosses: Vec<_> = graph
+                .nodes
+                .iter()
+                .filter(|node| node.depth == RUN_DEPTH)
+                .collect();
+            assert_eq!(bosses.len(), 1);
+            assert_eq!(bosses[0].kind, NodeKind::Boss);
+
+            // Every other node leads a row further down, so any path ends on the boss.
+            for node in graph.nodes.iter().filter(|node| node.depth < RUN_DEPTH) {
+                assert!(!node.next.is_empty(), "{:?} is a dead end", node);
+                for next in &node.next {
+                    assert_eq!(graph.nodes[*next].depth, node.depth + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_node_past_the_start_can_be_reached() {
+        for graph in graphs() {
+            for (index, node) in graph.nodes.iter().enumerate() {
+                let linked = graph.nodes.iter().any(|other| other.next.contains(&index));
+                assert!(node.depth == 0 || linked, "nothing leads to {:?}", node);
+            }
+        }
+    }
+
+    #[test]
+    fn the_same_seed_makes_the_same_graph() {
+        let first = RunGraph::generate(&mut RandomNumberGenerator::seeded(7));
+        let second = RunGraph::generate(&mut RandomNumberGenerator::seeded(7));
+
+        assert_eq!(format!("{:?}", first), format!("{:?}", second));
+    }
+}
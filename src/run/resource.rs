@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::unit::{Modifier, Stats, UnitKind};
+
+use super::{NodeKind, RunGraph, BATTLE_MAP, BOSS_MAP, BOSS_MODIFIERS, ELITE_MODIFIERS};
+
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct Run {
+    pub graph: RunGraph,
+    /// The node being played or last played.
+    pub current: Option<usize>,
+    pub gold: i32,
+    /// What happened on the last node, shown on the run map.
+    pub log: String,
+}
+
+impl Run {
+    /// Nodes the party can move to next.
+    pub fn options(&self) -> Vec<usize> {
+        match self.current {
+            Some(node) => self.graph.nodes[node].next.clone(),
+            None => self.graph.starts(),
+        }
+    }
+}
+
+/// A unit carried from battle to battle.
+#[derive(Clone, Debug)]
+pub struct Member {
+    pub kind: Handle<UnitKind>,
+    /// Stats as the last battle left them; `None` before the first battle.
+    pub stats: Option<Stats>,
+    /// Bonuses picked up along the run, on top of the kind's own modifiers.
+    pub modifiers: Vec<Modifier>,
+}
+
+/// Player units in the order they're deployed on the map's player spawns.
+#[derive(Resource, Default)]
+pub struct Party(pub Vec<Member>);
+
+/// The battle of the current node.
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct Encounter {
+    /// Path to the [`MapData`](crate::map::MapData) the battle is fought on.
+    pub map: String,
+    /// Modifiers every enemy of the battle is spawned with.
+    pub enemy_modifiers: Vec<Modifier>,
+}
+
+impl Encounter {
+    pub fn new(kind: NodeKind) -> Self {
+        let map = match kind {
+            NodeKind::Boss => BOSS_MAP,
+            _ => BATTLE_MAP,
+        };
+
+        let enemy_modifiers = match kind {
+            NodeKind::Elite => ELITE_MODIFIERS.to_vec(),
+            NodeKind::Boss => BOSS_MODIFIERS.to_vec(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            map: map.to_string(),
+            enemy_modifiers,
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::combat::Defeated;
+use crate::map::{CurrentMap, MapData};
+use crate::rng::{Rng, Stream};
+use crate::state::{DespawnOnExit, GameState};
+use crate::unit::{Faction, Modifier, Stat, Stats, Unit, UnitKind, PLAYER_FACTION};
+
+use super::{
+    Encounter, Member, NodeKind, Party, PartyMember, Run, RunGraph, RunMapScreen, GOLD_REWARD,
+    REST_HEAL, SHOP_PRICE, STARTING_PARTY,
+};
+
+const CHOICE_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+
+pub fn start_run(
+    asset_server: Res<AssetServer>,
+    mut rng: ResMut<Rng>,
+    mut run: ResMut<Run>,
+    mut party: ResMut<Party>,
+) {
+    *run = Run {
+        graph: RunGraph::generate(rng.stream(Stream::Run)),
+        ..default()
+    };
+
+    party.0 = STARTING_PARTY
+        .iter()
+        .map(|path| Member {
+            kind: asset_server.load(*path),
+            stats: None,
+            modifiers: Vec::new(),
+        })
+        .collect();
+}
+
+pub fn show_run_map(
+    mut commands: Commands,
+    run: Res<Run>,
+    party: Res<Party>,
+    kinds: Res<Assets<UnitKind>>,
+) {
+    let style = TextStyle {
+        font_size: 24.,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands.spawn((
+        RunMapScreen,
+        DespawnOnExit(GameState::RunMap),
+        TextBundle::from_section(describe(&run, &party, &kinds), style).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(24.),
+            left: Val::Px(24.),
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_run_map(
+    run: Res<Run>,
+    party: Res<Party>,
+    kinds: Res<Assets<UnitKind>>,
+    mut screen_query: Query<&mut Text, With<RunMapScreen>>,
+) {
+    for mut text in &mut screen_query {
+        text.sections[0].value = describe(&run, &party, &kinds);
+    }
+}
+
+fn describe(run: &Run, party: &Party, kinds: &Assets<UnitKind>) -> String {
+    let members = party
+        .0
+        .iter()
+        .filter_map(|member| {
+            let kind = kinds.get(&member.kind)?;
+            let stats = member.stats.unwrap_or(kind.stats);
+            Some(format!("{} {}/{}", kind.name, stats.hp, stats.max_hp))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let depth = run
+        .current
+        .map_or(0, |node| run.graph.nodes[node].depth + 1);
+
+    let options = run
+        .options()
+        .iter()
+        .enumerate()
+        .map(|(choice, node)| format!("[{}] {:?}", choice + 1, run.graph.nodes[*node].kind))
+        .collect::<Vec<_>>()
+        .join("   ");
+
+    format!(
+        "Depth {}    Gold {}\nParty: {}\n\n{}\n\n{}",
+        depth, run.gold, members, options, run.log
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn choose_node(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keys: Res<Input<KeyCode>>,
+    mut run: ResMut<Run>,
+    mut party: ResMut<Party>,
+    mut rng: ResMut<Rng>,
+    mut next_state: ResMut<NextState<GameState>>,
+    kinds: Res<Assets<UnitKind>>,
+) {
+    let Some(choice) = CHOICE_KEYS.iter().position(|key| keys.just_pressed(*key)) else {
+        return;
+    };
+
+    let Some(node) = run.options().get(choice).copied() else {
+        return;
+    };
+
+    run.current = Some(node);
+    let kind = run.graph.nodes[node].kind;
+
+    match kind {
+        NodeKind::Battle | NodeKind::Elite | NodeKind::Boss => {
+            let encounter = Encounter::new(kind);
+            commands.insert_resource(CurrentMap(asset_server.load(encounter.map.clone())));
+            commands.insert_resource(encounter);
+            run.log = String::new();
+            next_state.set(GameState::Battle);
+        }
+        NodeKind::Shop => {
+            if run.gold < SHOP_PRICE {
+                run.log = format!("A whetstone costs {} gold.", SHOP_PRICE);
+                return;
+            }
+
+            let Some(member) = party.0.first_mut() else {
+                return;
+            };
+
+            run.gold -= SHOP_PRICE;
+            member.modifiers.push(Modifier {
+                stat: Stat::Attack,
+                amount: 1,
+            });
+            run.log = "Bought a whetstone, the party leader hits harder.".to_string();
+        }
+        NodeKind::Rest => {
+            for member in &mut party.0 {
+                if let Some(stats) = &mut member.stats {
+                    let heal = stats.max_hp * REST_HEAL / 100;
+                    stats.hp = (stats.hp + heal).min(stats.max_hp);
+                }
+            }
+            run.log = "The party rests.".to_string();
+        }
+        NodeKind::Event => {
+            let rng = rng.stream(Stream::Run);
+
+            if rng.range(0, 2) == 0 {
+                let gold = rng.range(GOLD_REWARD.0, GOLD_REWARD.1);
+                run.gold += gold;
+                run.log = format!("Found {} gold on the road.", gold);
+            } else {
+                for member in &mut party.0 {
+                    let Some(kind) = kinds.get(&member.kind) else {
+                        continue;
+                    };
+                    let stats = member.stats.get_or_insert(kind.stats);
+                    stats.hp = (stats.hp - rng.range(1, 6)).max(1);
+                }
+                run.log = "Ambushed on the road, everyone is hurt.".to_string();
+            }
+        }
+    }
+}
+
+/// Ends the battle once a faction has no units left, carrying the survivors back to the run.
+#[allow(clippy::too_many_arguments)]
+pub fn check_outcome(
+    maps: Res<Assets<MapData>>,
+    current_map: Res<CurrentMap>,
+    mut run: ResMut<Run>,
+    mut party: ResMut<Party>,
+    mut rng: ResMut<Rng>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut defeated: EventReader<Defeated>,
+    unit_query: Query<(Entity, &Faction, &Stats, Option<&PartyMember>), With<Unit>>,
+) {
+    let defeated: HashSet<Entity> = defeated.read().map(|event| event.entity).collect();
+
+    let remaining = unit_query
+        .iter()
+        .filter(|(entity, ..)| !defeated.contains(entity));
+    let (players, enemies): (Vec<_>, Vec<_>) =
+        remaining.partition(|(_, faction, ..)| **faction == PLAYER_FACTION);
+
+    if !players.is_empty() && !enemies.is_empty() {
+        return;
+    }
+
+    if players.is_empty() {
+        next_state.set(GameState::Defeat);
+        return;
+    }
+
+    let boss = run
+        .current
+        .is_some_and(|node| run.graph.nodes[node].kind == NodeKind::Boss);
+    if boss {
+        next_state.set(GameState::Victory);
+        return;
+    }
+
+    next_state.set(GameState::RunMap);
+
+    let survivors: HashMap<usize, Stats> = players
+        .iter()
+        .filter_map(|(_, _, stats, member)| member.map(|member| (member.0, **stats)))
+        .collect();
+
+    // Members beyond the map's player spawns sat this battle out.
+    let deployed = maps.get(&current_map.0).map_or(0, |data| {
+        data.spawns
+            .iter()
+            .filter(|spawn| Faction::from(spawn.side) == PLAYER_FACTION)
+            .count()
+    });
+
+    party.0 = party
+        .0
+        .iter()
+        .enumerate()
+        .filter_map(|(index, member)| {
+            if index >= deployed {
+                return Some(member.clone());
+            }
+
+            let stats = *survivors.get(&index)?;
+            Some(Member {
+                stats: Some(stats),
+                ..member.clone()
+            })
+        })
+        .collect();
+
+    let gold = rng.stream(Stream::Loot).range(GOLD_REWARD.0, GOLD_REWARD.1);
+    run.gold += gold;
+    run.log = format!("Victory! Found {} gold.", gold);
+}
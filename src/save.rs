@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::map::MapLoaded;
+use crate::state::GameState;
+use crate::unit::moving;
+
+mod data;
+mod systems;
+
+pub use data::*;
+
+const SAVE_PATH: &str = "save.ron";
+
+/// Bumped whenever [`SaveData`] changes shape; older saves are refused.
+const SAVE_VERSION: u32 = 1;
+
+const SAVE_KEY: KeyCode = KeyCode::F5;
+
+const LOAD_KEY: KeyCode = KeyCode::F9;
+
+const REPLAY_PATH: &str = "replay.ron";
+
+/// Writes the current battle, from its start, to the replay file.
+const RECORD_KEY: KeyCode = KeyCode::F7;
+
+const REPLAY_KEY: KeyCode = KeyCode::F8;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Loading), systems::apply_save)
+            .add_systems(OnExit(GameState::Battle), systems::clear_battle_start)
+            .add_systems(
+                Update,
+                (
+                    systems::save_game.run_if(
+                        in_state(GameState::RunMap)
+                            .or_else(in_state(GameState::Battle).and_then(not(moving()))),
+                    ),
+                    systems::load_game.run_if(not(in_state(GameState::Loading))),
+                    systems::snapshot_battle.run_if(
+                        in_state(GameState::Battle)
+                            .and_then(not(resource_exists::<BattleStart>()))
+                            .and_then(not(resource_exists::<SavedBattle>())),
+                    ),
+                    systems::record_replay.run_if(resource_exists::<BattleStart>()),
+                    systems::play_replay.run_if(not(in_state(GameState::Loading))),
+                    systems::restore_battle.run_if(
+                        in_state(GameState::Battle)
+                            .and_then(on_event::<MapLoaded>())
+                            .and_then(resource_exists::<SavedBattle>()),
+                    ),
+                ),
+            );
+    }
+}
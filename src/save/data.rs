@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::action::ActionRecord;
+use crate::combat::Weapon;
+use crate::map::CellData;
+use crate::rng::Rng;
+use crate::run::{Encounter, Run};
+use crate::turn::{Actions, ChargeTime, Phase, TurnMode};
+use crate::unit::{Faction, Modifier, SpawnOrder, Stats};
+
+/// Everything needed to resume a run, as written to the save file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveData {
+    pub version: u32,
+    pub rng: Rng,
+    pub run: Run,
+    pub party: Vec<MemberData>,
+    pub encounter: Encounter,
+    pub turn_mode: TurnMode,
+    /// Only present when saved mid-battle.
+    pub battle: Option<BattleData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MemberData {
+    /// Path to the member's [`UnitKind`](crate::unit::UnitKind).
+    pub kind: String,
+    pub stats: Option<Stats>,
+    pub modifiers: Vec<Modifier>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BattleData {
+    pub size: (i32, i32),
+    pub cells: Vec<CellData>,
+    pub units: Vec<UnitData>,
+    pub turn: TurnData,
+    pub fog: Vec<FogData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UnitData {
+    pub name: String,
+    pub sprite: usize,
+    pub faction: Faction,
+    /// Units stand on top of the stack at their coordinates, so no floor is kept.
+    pub coordinates: (i32, i32),
+    pub flip_x: bool,
+    pub stats: Stats,
+    pub modifiers: Vec<Modifier>,
+    pub weapon: Weapon,
+    pub actions: Actions,
+    pub charge_time: ChargeTime,
+    pub spawn_order: SpawnOrder,
+    pub party_member: Option<usize>,
+}
+
+/// Units are referred to by their index in [`BattleData::units`].
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TurnData {
+    pub number: u32,
+    pub phase: Phase,
+    pub selected: Option<usize>,
+    pub active: Option<usize>,
+    pub preview: Vec<usize>,
+}
+
+/// The cells a faction had seen; the ones in sight are worked out again from its units.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FogData {
+    pub faction: Faction,
+    pub seen: Vec<(i32, i32)>,
+}
+
+/// A battle as it started, followed by every action applied in it. Loading the start and
+/// feeding the actions back in plays the battle out the same way again.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub start: SaveData,
+    pub actions: Vec<ActionRecord>,
+}
+
+/// A save file read from disk, applied once the game is back in [`Loading`](crate::state::GameState::Loading).
+#[derive(Resource)]
+pub struct PendingSave(pub SaveData);
+
+/// The battle of a loaded save, rebuilt on the next [`MapLoaded`](crate::map::MapLoaded).
+#[derive(Resource)]
+pub struct SavedBattle(pub BattleData);
+
+/// Snapshot taken once the units of a battle are in place, kept as the start of its replay.
+#[derive(Resource)]
+pub struct BattleStart(pub SaveData);
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("could not access save file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not write save file: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("could not parse save file: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+    #[error("save file version {0} is not supported")]
+    Version(u32),
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::action::{ActionLog, Replaying};
+use crate::combat::Weapon;
+use crate::fog::FogOfWar;
+use crate::map::{CellData, Coordinates, CurrentMap, Map, Position, Side, Tile, TileData};
+use crate::rng::Rng;
+use crate::run::{Encounter, Member, Party, PartyMember, Run};
+use crate::state::{AfterLoading, GameState};
+use crate::turn::{Actions, ChargeTime, Initiative, Phase, ResumingPhase, TurnMode, TurnNumber};
+use crate::unit::{
+    spawn_unit, Faction, Mode, Modifiers, SelectedUnit, Selection, SpawnOrder, Stats, Unit,
+    UnitBundle, UnitSprites,
+};
+
+use super::{
+    BattleData, BattleStart, FogData, MemberData, PendingSave, Replay, SaveData, SaveError,
+    SavedBattle, TurnData, UnitData, LOAD_KEY, RECORD_KEY, REPLAY_KEY, REPLAY_PATH, SAVE_KEY,
+    SAVE_PATH, SAVE_VERSION,
+};
+
+type UnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        &'static Faction,
+        &'static Position,
+        &'static Stats,
+        &'static Modifiers,
+        &'static Weapon,
+        &'static Actions,
+        &'static ChargeTime,
+        &'static SpawnOrder,
+        &'static TextureAtlasSprite,
+        Option<&'static PartyMember>,
+    ),
+    With<Unit>,
+>;
+
+/// Resources making up the progress of a run.
+#[derive(SystemParam)]
+pub struct Progress<'w> {
+    rng: ResMut<'w, Rng>,
+    run: ResMut<'w, Run>,
+    party: ResMut<'w, Party>,
+    encounter: ResMut<'w, Encounter>,
+    turn_mode: ResMut<'w, TurnMode>,
+}
+
+#[derive(SystemParam)]
+pub struct Battle<'w, 's> {
+    map: Res<'w, Map>,
+    fog: Res<'w, FogOfWar>,
+    turn_number: Res<'w, TurnNumber>,
+    selection: Res<'w, Selection>,
+    phase: Res<'w, State<Phase>>,
+    initiative: Res<'w, Initiative>,
+    tile_query: Query<'w, 's, (&'static Position, &'static TextureAtlasSprite), With<Tile>>,
+    unit_query: UnitQuery<'w, 's>,
+}
+
+pub fn save_game(
+    keys: Res<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    asset_server: Res<AssetServer>,
+    progress: Progress,
+    battle: Battle,
+) {
+    if !keys.just_pressed(SAVE_KEY) {
+        return;
+    }
+
+    let battle = (*state.get() == GameState::Battle).then(|| battle_data(&battle));
+    let data = save_data(&asset_server, &progress, battle);
+
+    match write_ron(SAVE_PATH, &data) {
+        Ok(()) => info!("saved to {}", SAVE_PATH),
+        Err(error) => error!("{}", error),
+    }
+}
+
+/// Keeps the battle as it started so it can be written out with its [`ActionLog`] later.
+pub fn snapshot_battle(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    progress: Progress,
+    battle: Battle,
+    mut log: ResMut<ActionLog>,
+) {
+    if battle.unit_query.is_empty() {
+        return;
+    }
+
+    let data = save_data(&asset_server, &progress, Some(battle_data(&battle)));
+    commands.insert_resource(BattleStart(data));
+    log.0.clear();
+}
+
+pub fn clear_battle_start(mut commands: Commands) {
+    commands.remove_resource::<BattleStart>();
+}
+
+pub fn record_replay(keys: Res<Input<KeyCode>>, start: Res<BattleStart>, log: Res<ActionLog>) {
+    if !keys.just_pressed(RECORD_KEY) {
+        return;
+    }
+
+    let replay = Replay {
+        start: start.0.clone(),
+        actions: log.0.clone(),
+    };
+
+    match write_ron(REPLAY_PATH, &replay) {
+        Ok(()) => info!(
+            "recorded {} actions to {}",
+            replay.actions.len(),
+            REPLAY_PATH
+        ),
+        Err(error) => error!("{}", error),
+    }
+}
+
+/// Loads the start of the recorded battle and queues its actions to be applied again.
+pub fn play_replay(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keys.just_pressed(REPLAY_KEY) {
+        return;
+    }
+
+    let replay = read_ron::<Replay>(REPLAY_PATH).and_then(|replay| match replay.start.version {
+        SAVE_VERSION => Ok(replay),
+        version => Err(SaveError::Version(version)),
+    });
+
+    match replay {
+        Ok(replay) => {
+            commands.insert_resource(PendingSave(replay.start));
+            commands.insert_resource(Replaying(replay.actions.into()));
+            next_state.set(GameState::Loading);
+        }
+        Err(error) => error!("{}", error),
+    }
+}
+
+fn save_data(
+    asset_server: &AssetServer,
+    progress: &Progress,
+    battle: Option<BattleData>,
+) -> SaveData {
+    let party = progress
+        .party
+        .0
+        .iter()
+        .map(|member| MemberData {
+            kind: asset_server
+                .get_path(member.kind.id())
+                .map(|path| path.to_string())
+                .unwrap_or_default(),
+            stats: member.stats,
+            modifiers: member.modifiers.clone(),
+        })
+        .collect();
+
+    SaveData {
+        version: SAVE_VERSION,
+        rng: progress.rng.clone(),
+        run: progress.run.clone(),
+        party,
+        encounter: progress.encounter.clone(),
+        turn_mode: *progress.turn_mode,
+        battle,
+    }
+}
+
+fn battle_data(battle: &Battle) -> BattleData {
+    let mut stacks: HashMap<(i32, i32), Vec<TileData>> = HashMap::new();
+    for (position, sprite) in &battle.tile_query {
+        let coordinates = (position.coordinates.0, position.coordinates.1);
+        stacks.entry(coordinates).or_default().push(TileData {
+            index: sprite.index,
+            floor: position.floor.0,
+        });
+    }
+
+    let mut cells: Vec<CellData> = stacks
+        .into_iter()
+        .map(|(coordinates, mut stack)| {
+            stack.sort_by_key(|tile| tile.floor);
+            CellData { coordinates, stack }
+        })
+        .collect();
+    cells.sort_by_key(|cell| (cell.coordinates.1, cell.coordinates.0));
+
+    let mut entities = Vec::new();
+    let units = battle
+        .unit_query
+        .iter()
+        .map(|unit| {
+            let (
+                entity,
+                name,
+                faction,
+                position,
+                stats,
+                modifiers,
+                weapon,
+                actions,
+                charge_time,
+                spawn_order,
+                sprite,
+                member,
+            ) = unit;
+            entities.push(entity);
+
+            UnitData {
+                name: name.as_str().to_string(),
+                sprite: sprite.index,
+                faction: *faction,
+                coordinates: (position.coordinates.0, position.coordinates.1),
+                flip_x: sprite.flip_x,
+                stats: *stats,
+                modifiers: modifiers.0.clone(),
+                weapon: *weapon,
+                actions: *actions,
+                charge_time: *charge_time,
+                spawn_order: *spawn_order,
+                party_member: member.map(|member| member.0),
+            }
+        })
+        .collect();
+
+    let index_of = |entity: Entity| entities.iter().position(|e| *e == entity);
+
+    let selected = battle
+        .selection
+        .0
+        .as_ref()
+        .and_then(|selected_unit| index_of(selected_unit.entity));
+
+    let fog = [Faction::Player, Faction::Enemy]
+        .into_iter()
+        .map(|faction| {
+            let mut seen: Vec<(i32, i32)> = battle
+                .fog
+                .seen(faction)
+                .map(|coordinates| (coordinates.0, coordinates.1))
+                .collect();
+            seen.sort_by_key(|(x, y)| (*y, *x));
+            FogData { faction, seen }
+        })
+        .collect();
+
+    BattleData {
+        size: (battle.map.size.x as i32, battle.map.size.y as i32),
+        cells,
+        units,
+        turn: TurnData {
+            number: battle.turn_number.0,
+            phase: *battle.phase.get(),
+            selected,
+            active: battle.initiative.active.and_then(index_of),
+            preview: battle
+                .initiative
+                .preview
+                .iter()
+                .filter_map(|entity| index_of(*entity))
+                .collect(),
+        },
+        fog,
+    }
+}
+
+fn write_ron<T: Serialize>(path: &str, data: &T) -> Result<(), SaveError> {
+    let ron = ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, ron)?;
+    Ok(())
+}
+
+fn read_ron<T: DeserializeOwned>(path: &str) -> Result<T, SaveError> {
+    let ron = std::fs::read_to_string(path)?;
+    Ok(ron::de::from_str(&ron)?)
+}
+
+fn read_save() -> Result<SaveData, SaveError> {
+    let data: SaveData = read_ron(SAVE_PATH)?;
+
+    if data.version != SAVE_VERSION {
+        return Err(SaveError::Version(data.version));
+    }
+
+    Ok(data)
+}
+
+/// Reads the save file and goes back through loading so the current screen is torn down.
+pub fn load_game(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keys.just_pressed(LOAD_KEY) {
+        return;
+    }
+
+    match read_save() {
+        Ok(data) => {
+            commands.insert_resource(PendingSave(data));
+            next_state.set(GameState::Loading);
+        }
+        Err(error) => error!("{}", error),
+    }
+}
+
+pub fn apply_save(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Option<ResMut<PendingSave>>,
+    mut after_loading: ResMut<AfterLoading>,
+    mut progress: Progress,
+) {
+    let Some(mut pending) = pending else {
+        return;
+    };
+    let data = &mut pending.0;
+
+    *progress.rng = data.rng.clone();
+    *progress.run = data.run.clone();
+    *progress.encounter = data.encounter.clone();
+    commands.insert_resource(CurrentMap(asset_server.load(data.encounter.map.clone())));
+    *progress.turn_mode = data.turn_mode;
+    progress.party.0 = data
+        .party
+        .iter()
+        .map(|member| Member {
+            kind: asset_server.load(member.kind.clone()),
+            stats: member.stats,
+            modifiers: member.modifiers.clone(),
+        })
+        .collect();
+
+    after_loading.0 = Some(match data.battle.take() {
+        Some(battle) => {
+            commands.insert_resource(SavedBattle(battle));
+            GameState::Battle
+        }
+        None => GameState::RunMap,
+    });
+
+    commands.remove_resource::<PendingSave>();
+}
+
+/// Spawns the units of a saved battle on the map rebuilt from it, in place of the map's spawns.
+#[allow(clippy::too_many_arguments)]
+pub fn restore_battle(
+    mut commands: Commands,
+    saved: Res<SavedBattle>,
+    sprites: Res<UnitSprites>,
+    mut map: ResMut<Map>,
+    mut turn_number: ResMut<TurnNumber>,
+    mut selection: ResMut<Selection>,
+    mut initiative: ResMut<Initiative>,
+    phase: Res<State<Phase>>,
+    mut next_phase: ResMut<NextState<Phase>>,
+) {
+    let battle = &saved.0;
+
+    let entities: Vec<Entity> = battle
+        .units
+        .iter()
+        .map(|unit| {
+            let bundle = UnitBundle {
+                name: Name::new(unit.name.clone()),
+                faction: unit.faction,
+                stats: unit.stats,
+                modifiers: Modifiers(unit.modifiers.clone()),
+                weapon: unit.weapon,
+                actions: unit.actions,
+                charge_time: unit.charge_time,
+                spawn_order: unit.spawn_order,
+                ..default()
+            };
+
+            let (x, y) = unit.coordinates;
+            let mut entity = spawn_unit(
+                &mut commands,
+                &mut map,
+                &sprites,
+                unit.sprite,
+                Coordinates(x, y, Side::Center),
+                unit.flip_x,
+                bundle,
+            );
+            if let Some(index) = unit.party_member {
+                entity.insert(PartyMember(index));
+            }
+            entity.id()
+        })
+        .collect();
+
+    turn_number.0 = battle.turn.number;
+    selection.0 = battle.turn.selected.map(|index| SelectedUnit {
+        entity: entities[index],
+        movement: None,
+        mode: Mode::Move,
+    });
+    initiative.active = battle.turn.active.map(|index| entities[index]);
+    initiative.preview = battle
+        .turn
+        .preview
+        .iter()
+        .map(|index| entities[*index])
+        .collect();
+
+    // Inserted through commands so that it lands after the fog is reset for the new map.
+    let mut fog = FogOfWar::default();
+    for data in &battle.fog {
+        let seen = data
+            .seen
+            .iter()
+            .map(|(x, y)| Coordinates(*x, *y, Side::Center));
+        fog.remember(data.faction, seen);
+    }
+    commands.insert_resource(fog);
+
+    // Entering the saved phase mustn't reset the actions taken in it before saving.
+    if *phase.get() != battle.turn.phase {
+        commands.insert_resource(ResumingPhase);
+    }
+    next_phase.set(battle.turn.phase);
+    commands.remove_resource::<SavedBattle>();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng::Stream;
+    use crate::run::{NodeKind, RunGraph};
+    use crate::unit::{Modifier, Stat};
+
+    use super::*;
+
+    fn battle() -> BattleData {
+        let stats = Stats {
+            max_hp: 10,
+            hp: 7,
+            move_range: 4,
+            jump: 1,
+            speed: 6,
+            ..Default::default()
+        };
+
+        BattleData {
+            size: (2, 1),
+            cells: vec![
+                CellData {
+                    coordinates: (0, 0),
+                    stack: vec![TileData {
+                        index: 92,
+                        floor: 0,
+                    }],
+                },
+                CellData {
+                    coordinates: (1, 0),
+                    stack: vec![
+                        TileData {
+                            index: 92,
+                            floor: 0,
+                        },
+                        TileData {
+                            index: 93,
+                            floor: 1,
+                        },
+                    ],
+                },
+            ],
+            units: vec![UnitData {
+                name: "Knight".to_string(),
+                sprite: 3,
+                faction: Faction::Player,
+                coordinates: (1, 0),
+                flip_x: false,
+                stats,
+                modifiers: vec![Modifier {
+                    stat: Stat::Defense,
+                    amount: 1,
+                }],
+                weapon: Weapon::default(),
+                actions: Actions {
+                    moved: true,
+                    acted: false,
+                },
+                charge_time: ChargeTime(40),
+                spawn_order: SpawnOrder(0),
+                party_member: Some(0),
+            }],
+            turn: TurnData {
+                number: 3,
+                phase: Phase::Enemy,
+                selected: Some(0),
+                active: None,
+                preview: vec![0],
+            },
+            fog: vec![FogData {
+                faction: Faction::Player,
+                seen: vec![(0, 0), (1, 0)],
+            }],
+        }
+    }
+
+    fn save(rng: &mut Rng) -> SaveData {
+        SaveData {
+            version: SAVE_VERSION,
+            run: Run {
+                graph: RunGraph::generate(rng.stream(Stream::Run)),
+                current: Some(0),
+                gold: 25,
+                log: "Won a battle".to_string(),
+            },
+            rng: rng.clone(),
+            party: vec![MemberData {
+                kind: "units/knight.unit.ron".to_string(),
+                stats: None,
+                modifiers: Vec::new(),
+            }],
+            encounter: Encounter::new(NodeKind::Elite),
+            turn_mode: TurnMode::ChargeTime,
+            battle: Some(battle()),
+        }
+    }
+
+    fn to_ron(data: &SaveData) -> String {
+        ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn saves_round_trip_through_the_file() {
+        let path = std::env::temp_dir().join(format!("save-{}.ron", std::process::id()));
+        let path = path.to_str().unwrap();
+        let data = save(&mut Rng::new(11));
+
+        write_ron(path, &data).unwrap();
+        let read: SaveData = read_ron(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(to_ron(&read), to_ron(&data));
+        let battle = read.battle.unwrap();
+        assert_eq!(battle.units[0].charge_time.0, 40);
+        assert_eq!(battle.turn.phase, Phase::Enemy);
+        assert_eq!(battle.fog[0].seen, vec![(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn loaded_random_streams_carry_on_where_they_were_saved() {
+        let mut rng = Rng::new(11);
+        let data = save(&mut rng);
+        rng.stream(Stream::Combat).range(0, 100);
+        let mut saved = rng.clone();
+        let read: SaveData = ron::de::from_str(&to_ron(&SaveData {
+            rng: saved.clone(),
+            ..data
+        }))
+        .unwrap();
+        let mut loaded = read.rng;
+
+        for stream in [Stream::Map, Stream::Combat, Stream::Run, Stream::Loot] {
+            let draws = |rng: &mut Rng| -> Vec<i32> {
+                (0..20).map(|_| rng.stream(stream).range(0, 1000)).collect()
+            };
+            assert_eq!(draws(&mut loaded), draws(&mut saved), "{stream:?}");
+        }
+    }
+}
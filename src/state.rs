@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+mod components;
+mod resource;
+mod systems;
+
+pub use components::*;
+pub use resource::*;
+
+const SCREEN_FONT_SIZE: f32 = 32.;
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .init_resource::<AfterLoading>()
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                systems::show_screen("Tactical Roguelike\n\nPress Enter to start a run"),
+            )
+            .add_systems(
+                OnEnter(GameState::Victory),
+                systems::show_screen("Victory! The boss is defeated.\n\nPress Enter"),
+            )
+            .add_systems(
+                OnEnter(GameState::Defeat),
+                systems::show_screen("The party has fallen.\n\nPress Enter"),
+            )
+            .add_systems(
+                Update,
+                (
+                    systems::finish_loading.run_if(in_state(GameState::Loading)),
+                    systems::leave_screen.run_if(
+                        in_state(GameState::MainMenu)
+                            .or_else(in_state(GameState::Victory))
+                            .or_else(in_state(GameState::Defeat)),
+                    ),
+                ),
+            );
+
+        for state in [
+            GameState::Loading,
+            GameState::MainMenu,
+            GameState::RunMap,
+            GameState::Battle,
+            GameState::Victory,
+            GameState::Defeat,
+        ] {
+            app.add_systems(OnExit(state), systems::despawn_scoped(state));
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use bevy::prelude::*;
+
+use super::GameState;
+
+/// Despawns the entity, and its children, when the game leaves this state.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct DespawnOnExit(pub GameState);
@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+
+/// Which part of the game is on screen.
+#[derive(States, Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub enum GameState {
+    /// Waiting for the map of a battle resumed from a save.
+    #[default]
+    Loading,
+    MainMenu,
+    RunMap,
+    Battle,
+    Victory,
+    Defeat,
+}
+
+/// State to go to once loading is done, instead of the main menu.
+#[derive(Resource, Default)]
+pub struct AfterLoading(pub Option<GameState>);
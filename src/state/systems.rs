@@ -0,0 +1,80 @@
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::prelude::*;
+
+use crate::map::CurrentMap;
+use crate::save::SavedBattle;
+
+use super::{AfterLoading, DespawnOnExit, GameState, SCREEN_FONT_SIZE};
+
+pub fn finish_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_map: Option<Res<CurrentMap>>,
+    mut after_loading: ResMut<AfterLoading>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Only a saved battle has a map to wait for.
+    let load_state = current_map.map_or(RecursiveDependencyLoadState::Loaded, |current_map| {
+        asset_server.recursive_dependency_load_state(&current_map.0)
+    });
+
+    match load_state {
+        RecursiveDependencyLoadState::Loaded => {
+            next_state.set(after_loading.0.take().unwrap_or(GameState::MainMenu));
+        }
+        RecursiveDependencyLoadState::Failed => {
+            error!("could not load the map of the saved battle");
+            commands.remove_resource::<SavedBattle>();
+            after_loading.0 = None;
+            next_state.set(GameState::MainMenu);
+        }
+        _ => {}
+    }
+}
+
+pub fn show_screen(text: &'static str) -> impl FnMut(Commands, Res<State<GameState>>) {
+    move |mut commands: Commands, state: Res<State<GameState>>| {
+        let style = TextStyle {
+            font_size: SCREEN_FONT_SIZE,
+            color: Color::WHITE,
+            ..default()
+        };
+
+        commands.spawn((
+            DespawnOnExit(*state.get()),
+            TextBundle::from_section(text, style).with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.),
+                left: Val::Px(48.),
+                ..default()
+            }),
+        ));
+    }
+}
+
+/// Moves on from the main menu and the end of run screens.
+pub fn leave_screen(
+    keys: Res<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    match state.get() {
+        GameState::MainMenu => next_state.set(GameState::RunMap),
+        GameState::Victory | GameState::Defeat => next_state.set(GameState::MainMenu),
+        _ => {}
+    }
+}
+
+pub fn despawn_scoped(state: GameState) -> impl FnMut(Commands, Query<(Entity, &DespawnOnExit)>) {
+    move |mut commands: Commands, scoped_query: Query<(Entity, &DespawnOnExit)>| {
+        for (entity, scope) in &scoped_query {
+            if scope.0 == state {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
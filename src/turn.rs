@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+
+use crate::action::{IssueActions, Replaying};
+use crate::save::SavedBattle;
+use crate::state::GameState;
+use crate::unit::moving;
+
+mod components;
+mod events;
+mod resource;
+mod systems;
+
+pub use components::*;
+pub use events::*;
+pub use resource::*;
+
+/// Charge time a unit needs to take its turn in [`TurnMode::ChargeTime`].
+pub const CHARGE_THRESHOLD: i32 = 100;
+
+/// Charge time spent by a unit that neither moved nor acted; moving and acting cost 20 each.
+const WAIT_COST: i32 = 60;
+
+const ACTION_COST: i32 = 20;
+
+const PREVIEW_LENGTH: usize = 5;
+
+/// Kept off Return, which confirms the menu screens.
+const END_TURN_KEY: KeyCode = KeyCode::T;
+
+pub struct TurnPlugin {
+    /// How control passes between units in every battle of the run.
+    pub mode: TurnMode,
+}
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        info!("turn mode: {:?}", self.mode);
+
+        app.add_state::<Phase>()
+            .insert_resource(self.mode)
+            .init_resource::<TurnNumber>()
+            .init_resource::<Initiative>()
+            .register_type::<TurnMode>()
+            .register_type::<Actions>()
+            .register_type::<ChargeTime>()
+            .register_type::<Initiative>()
+            .add_event::<TurnStarted>()
+            .add_event::<PhaseChanged>()
+            .add_event::<EndTurn>()
+            .add_systems(
+                OnEnter(Phase::Player),
+                systems::start_phase.run_if(in_state(GameState::Battle)),
+            )
+            .add_systems(
+                OnEnter(Phase::Enemy),
+                systems::start_phase.run_if(in_state(GameState::Battle)),
+            )
+            .add_systems(
+                OnEnter(GameState::Battle),
+                (
+                    // Every battle begins in the player phase, which is usually entered already.
+                    systems::start_phase.run_if(not(resource_exists::<SavedBattle>())),
+                    systems::show_initiative.run_if(resource_equals(TurnMode::ChargeTime)),
+                ),
+            )
+            .add_systems(OnExit(GameState::Battle), systems::reset_turns)
+            .add_systems(
+                Update,
+                (
+                    systems::track_actions,
+                    systems::end_turn_input.in_set(IssueActions).run_if(
+                        in_state(Phase::Player).and_then(not(resource_exists::<Replaying>())),
+                    ),
+                    systems::finish_phase
+                        .run_if(resource_equals(TurnMode::Phases).and_then(not(moving()))),
+                    systems::end_turn.run_if(resource_equals(TurnMode::Phases)),
+                    systems::finish_activation
+                        .run_if(resource_equals(TurnMode::ChargeTime).and_then(not(moving()))),
+                    systems::activate_next.run_if(resource_equals(TurnMode::ChargeTime)),
+                    systems::update_initiative.run_if(resource_changed::<Initiative>()),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Battle)),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use crate::action::Action;
+    use crate::combat::Attacked;
+    use crate::unit::{Selection, Stepped};
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state::<GameState>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Selection>()
+            .add_event::<Action>()
+            .add_event::<Stepped>()
+            .add_event::<Attacked>()
+            .add_plugins(TurnPlugin {
+                mode: TurnMode::Phases,
+            });
+        app.update();
+        app
+    }
+
+    fn enter(app: &mut App, state: GameState) {
+        app.world.resource_mut::<NextState<GameState>>().set(state);
+        app.update();
+    }
+
+    fn started(app: &App, reader: &mut ManualEventReader<TurnStarted>) -> Vec<u32> {
+        let events = app.world.resource::<Events<TurnStarted>>();
+        reader.read(events).map(|event| event.number).collect()
+    }
+
+    #[test]
+    fn every_battle_starts_on_turn_one() {
+        let mut app = app();
+        let mut reader = ManualEventReader::default();
+        assert!(started(&app, &mut reader).is_empty());
+
+        enter(&mut app, GameState::Battle);
+        assert_eq!(started(&app, &mut reader), [1]);
+
+        // The first battle ends in the player phase, the second in the enemy phase.
+        enter(&mut app, GameState::RunMap);
+        enter(&mut app, GameState::Battle);
+        assert_eq!(started(&app, &mut reader), [1]);
+        assert_eq!(app.world.resource::<TurnNumber>().0, 1);
+
+        app.world.send_event(EndTurn);
+        app.update();
+        app.update();
+        assert_eq!(*app.world.resource::<State<Phase>>().get(), Phase::Enemy);
+
+        enter(&mut app, GameState::RunMap);
+        enter(&mut app, GameState::Battle);
+        assert_eq!(started(&app, &mut reader), [1]);
+        assert_eq!(*app.world.resource::<State<Phase>>().get(), Phase::Player);
+        assert_eq!(app.world.resource::<TurnNumber>().0, 1);
+    }
+}
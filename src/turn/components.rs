@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What a unit has already done during its faction's phase.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Default, Debug)]
+#[reflect(Component)]
+pub struct Actions {
+    pub moved: bool,
+    pub acted: bool,
+}
+
+impl Actions {
+    pub fn can_move(&self) -> bool {
+        !self.moved
+    }
+
+    pub fn can_act(&self) -> bool {
+        !self.acted
+    }
+}
+
+/// Text listing the active unit and the ones expected to act after it.
+#[derive(Component)]
+pub struct InitiativeDisplay;
+
+/// Charge accumulated from speed; the unit acts once it reaches [`CHARGE_THRESHOLD`](super::CHARGE_THRESHOLD).
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Default, Debug)]
+#[reflect(Component)]
+pub struct ChargeTime(pub i32);
@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+
+use super::Phase;
+
+/// Sent at the start of every player phase.
+#[derive(Event, Debug)]
+pub struct TurnStarted {
+    pub number: u32,
+}
+
+#[derive(Event, Debug)]
+pub struct PhaseChanged {
+    pub phase: Phase,
+}
+
+/// Ends the current phase and hands control to the next faction.
+#[derive(Event, Debug)]
+pub struct EndTurn;
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::unit::Faction;
+
+use super::CHARGE_THRESHOLD;
+
+/// Counts player phases, or activations in [`TurnMode::ChargeTime`].
+#[derive(Resource, Default, Debug)]
+pub struct TurnNumber(pub u32);
+
+/// Present while a resumed battle enters the phase it was saved in, so that the phase carries
+/// on from the save instead of starting afresh.
+#[derive(Resource, Debug)]
+pub struct ResumingPhase;
+
+/// How control passes between units.
+#[derive(Resource, Reflect, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Default, Debug)]
+#[reflect(Resource)]
+pub enum TurnMode {
+    /// Each faction moves all of its units, then hands over to the other.
+    #[default]
+    Phases,
+    /// Units act one at a time in the order their [`ChargeTime`](super::ChargeTime) fills up.
+    ChargeTime,
+}
+
+#[derive(Error, Debug)]
+#[error("unknown turn mode {0:?}, expected \"phases\" or \"charge-time\"")]
+pub struct UnknownTurnMode(String);
+
+impl FromStr for TurnMode {
+    type Err = UnknownTurnMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "phases" => Ok(TurnMode::Phases),
+            "charge-time" => Ok(TurnMode::ChargeTime),
+            _ => Err(UnknownTurnMode(s.to_string())),
+        }
+    }
+}
+
+#[derive(States, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub enum Phase {
+    #[default]
+    Player,
+    Enemy,
+}
+
+impl Phase {
+    pub fn faction(&self) -> Faction {
+        match self {
+            Phase::Player => Faction::Player,
+            Phase::Enemy => Faction::Enemy,
+        }
+    }
+
+    pub fn next(&self) -> Phase {
+        match self {
+            Phase::Player => Phase::Enemy,
+            Phase::Enemy => Phase::Player,
+        }
+    }
+}
+
+/// The unit acting in [`TurnMode::ChargeTime`] and the ones expected to follow it.
+#[derive(Resource, Reflect, Default, Debug)]
+#[reflect(Resource)]
+pub struct Initiative {
+    pub active: Option<Entity>,
+    pub preview: Vec<Entity>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Charger {
+    pub entity: Entity,
+    pub charge_time: i32,
+    pub speed: i32,
+    /// The unit's [`SpawnOrder`](crate::unit::SpawnOrder).
+    pub order: u32,
+}
+
+impl Initiative {
+    /// Ticks charge time until a unit is ready and returns it, leaving its charge untouched.
+    /// Ties go to the higher charge, then the higher speed, then the unit spawned first.
+    pub fn next_actor(chargers: &mut [Charger]) -> Option<Entity> {
+        loop {
+            let ready = chargers
+                .iter()
+                .filter(|charger| charger.charge_time >= CHARGE_THRESHOLD)
+                .max_by(|a, b| {
+                    a.charge_time
+                        .cmp(&b.charge_time)
+                        .then(a.speed.cmp(&b.speed))
+                        .then(b.order.cmp(&a.order))
+                });
+
+            if let Some(charger) = ready {
+                return Some(charger.entity);
+            }
+
+            if chargers.iter().all(|charger| charger.speed <= 0) {
+                return None;
+            }
+
+            chargers
+                .iter_mut()
+                .for_each(|charger| charger.charge_time += charger.speed.max(0));
+        }
+    }
+
+    /// The next `count` actors, assuming each of them spends a full turn.
+    pub fn preview(chargers: &[Charger], count: usize) -> Vec<Entity> {
+        let mut chargers = chargers.to_vec();
+
+        (0..count)
+            .map_while(|_| {
+                let entity = Self::next_actor(&mut chargers)?;
+                let charger = chargers.iter_mut().find(|c| c.entity == entity)?;
+                charger.charge_time -= CHARGE_THRESHOLD;
+                Some(entity)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charger(index: u32, charge_time: i32, speed: i32, order: u32) -> Charger {
+        Charger {
+            entity: Entity::from_raw(index),
+            charge_time,
+            speed,
+            order,
+        }
+    }
+
+    #[test]
+    fn next_actor_ticks_until_someone_is_ready() {
+        let mut chargers = [charger(0, 0, 10, 0), charger(1, 0, 25, 1)];
+
+        assert_eq!(
+            Initiative::next_actor(&mut chargers),
+            Some(Entity::from_raw(1))
+        );
+        assert_eq!(chargers[0].charge_time, 40);
+        assert_eq!(chargers[1].charge_time, 100);
+    }
+
+    #[test]
+    fn next_actor_breaks_ties_on_charge_then_speed() {
+        let mut chargers = [charger(0, 120, 5, 0), charger(1, 130, 5, 1)];
+        assert_eq!(
+            Initiative::next_actor(&mut chargers),
+            Some(Entity::from_raw(1))
+        );
+
+        let mut chargers = [charger(0, 100, 5, 0), charger(1, 100, 8, 1)];
+        assert_eq!(
+            Initiative::next_actor(&mut chargers),
+            Some(Entity::from_raw(1))
+        );
+    }
+
+    #[test]
+    fn next_actor_breaks_full_ties_on_spawn_order_not_entity() {
+        // Entity indices are reused after despawns, so a later spawn can have the lower index.
+        let mut chargers = [charger(3, 100, 5, 1), charger(7, 100, 5, 0)];
+
+        assert_eq!(
+            Initiative::next_actor(&mut chargers),
+            Some(Entity::from_raw(7))
+        );
+    }
+
+    #[test]
+    fn next_actor_gives_up_without_speed() {
+        let mut chargers = [charger(0, 0, 0, 0), charger(1, 50, -3, 1)];
+
+        assert_eq!(Initiative::next_actor(&mut chargers), None);
+    }
+
+    #[test]
+    fn preview_spends_a_full_turn_per_actor() {
+        let chargers = [charger(0, 0, 50, 0), charger(1, 0, 25, 1)];
+
+        let preview = Initiative::preview(&chargers, 4);
+
+        let order: Vec<u32> = preview.iter().map(|entity| entity.index()).collect();
+        assert_eq!(order, [0, 0, 1, 0]);
+    }
+}
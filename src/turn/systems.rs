@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+
+use crate::action::Action;
+use crate::combat::Attacked;
+use crate::state::{DespawnOnExit, GameState};
+use crate::unit::{
+    Faction, Mode, Modifiers, SelectedUnit, Selection, SpawnOrder, Stats, Stepped, Unit,
+};
+
+use super::{
+    Actions, ChargeTime, Charger, EndTurn, Initiative, InitiativeDisplay, Phase, PhaseChanged,
+    ResumingPhase, TurnMode, TurnNumber, TurnStarted, ACTION_COST, END_TURN_KEY, PREVIEW_LENGTH,
+    WAIT_COST,
+};
+
+type ChargerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Faction,
+        &'static Stats,
+        &'static Modifiers,
+        &'static SpawnOrder,
+        &'static mut ChargeTime,
+        &'static mut Actions,
+    ),
+    With<Unit>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_phase(
+    mut commands: Commands,
+    resuming: Option<Res<ResumingPhase>>,
+    phase: Res<State<Phase>>,
+    mode: Res<TurnMode>,
+    mut selection: ResMut<Selection>,
+    mut turn_number: ResMut<TurnNumber>,
+    mut turn_started: EventWriter<TurnStarted>,
+    mut phase_changed: EventWriter<PhaseChanged>,
+    mut unit_query: Query<(&Faction, &mut Actions), With<Unit>>,
+) {
+    let phase = *phase.get();
+
+    // In charge time mode the phase only follows the faction of the active unit.
+    if *mode == TurnMode::ChargeTime || resuming.is_some() {
+        commands.remove_resource::<ResumingPhase>();
+        phase_changed.send(PhaseChanged { phase });
+        return;
+    }
+
+    selection.0 = None;
+
+    unit_query
+        .iter_mut()
+        .filter(|(faction, _)| **faction == phase.faction())
+        .for_each(|(_, mut actions)| *actions = Actions::default());
+
+    if phase == Phase::Player {
+        turn_number.0 += 1;
+        turn_started.send(TurnStarted {
+            number: turn_number.0,
+        });
+    }
+
+    debug!("turn {}: {:?} phase", turn_number.0, phase);
+    phase_changed.send(PhaseChanged { phase });
+}
+
+pub fn track_actions(
+    mut stepped: EventReader<Stepped>,
+    mut attacked: EventReader<Attacked>,
+    mut actions_query: Query<&mut Actions>,
+) {
+    for event in stepped.read() {
+        if let Ok(mut actions) = actions_query.get_mut(event.entity) {
+            actions.moved = true;
+        }
+    }
+
+    for event in attacked.read() {
+        if let Ok(mut actions) = actions_query.get_mut(event.attacker) {
+            actions.acted = true;
+        }
+    }
+}
+
+pub fn end_turn_input(keys: Res<Input<KeyCode>>, mut actions: EventWriter<Action>) {
+    if keys.just_pressed(END_TURN_KEY) {
+        actions.send(Action::EndTurn);
+    }
+}
+
+/// Ends the phase on its own once every unit of the current faction has acted.
+pub fn finish_phase(
+    phase: Res<State<Phase>>,
+    mut end_turn: EventWriter<EndTurn>,
+    unit_query: Query<(&Faction, &Actions), With<Unit>>,
+) {
+    let mut units = unit_query
+        .iter()
+        .filter(|(faction, _)| **faction == phase.get().faction())
+        .peekable();
+
+    if units.peek().is_none() {
+        return;
+    }
+
+    if units.all(|(_, actions)| actions.acted) {
+        end_turn.send(EndTurn);
+    }
+}
+
+pub fn end_turn(
+    phase: Res<State<Phase>>,
+    mut next_phase: ResMut<NextState<Phase>>,
+    mut end_turn: EventReader<EndTurn>,
+) {
+    if end_turn.read().count() == 0 {
+        return;
+    }
+
+    next_phase.set(phase.get().next());
+}
+
+/// Ends the active unit's turn on [`EndTurn`] or once it has both moved and acted.
+pub fn finish_activation(
+    mut initiative: ResMut<Initiative>,
+    mut selection: ResMut<Selection>,
+    mut end_turn: EventReader<EndTurn>,
+    mut unit_query: Query<(&Actions, &mut ChargeTime), With<Unit>>,
+) {
+    let ended = end_turn.read().count() > 0;
+
+    let Some(active) = initiative.active else {
+        return;
+    };
+
+    let Ok((actions, mut charge_time)) = unit_query.get_mut(active) else {
+        // The active unit was defeated.
+        initiative.active = None;
+        return;
+    };
+
+    let finished = ended || (actions.moved && actions.acted);
+    if !finished {
+        return;
+    }
+
+    let spent = [actions.moved, actions.acted]
+        .iter()
+        .filter(|done| **done)
+        .count() as i32;
+    charge_time.0 -= WAIT_COST + spent * ACTION_COST;
+
+    initiative.active = None;
+    selection.0 = None;
+}
+
+/// Picks the next unit to act once the previous one has finished.
+pub fn activate_next(
+    mut initiative: ResMut<Initiative>,
+    mut selection: ResMut<Selection>,
+    mut turn_number: ResMut<TurnNumber>,
+    phase: Res<State<Phase>>,
+    mut next_phase: ResMut<NextState<Phase>>,
+    mut turn_started: EventWriter<TurnStarted>,
+    mut unit_query: ChargerQuery,
+) {
+    if initiative.active.is_some() {
+        return;
+    }
+
+    let mut chargers = unit_query
+        .iter()
+        .map(
+            |(entity, _, stats, modifiers, spawn_order, charge_time, _)| Charger {
+                entity,
+                charge_time: charge_time.0,
+                speed: stats.with(modifiers).speed,
+                order: spawn_order.0,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let Some(active) = Initiative::next_actor(&mut chargers) else {
+        return;
+    };
+
+    let mut faction = None;
+    for (entity, unit_faction, _, _, _, mut charge_time, mut actions) in &mut unit_query {
+        let charger = chargers.iter().find(|c| c.entity == entity).unwrap();
+        charge_time.0 = charger.charge_time;
+
+        // Only the active unit may move or act.
+        *actions = if entity == active {
+            faction = Some(*unit_faction);
+            Actions::default()
+        } else {
+            Actions {
+                moved: true,
+                acted: true,
+            }
+        };
+    }
+
+    if let Some(charger) = chargers.iter_mut().find(|c| c.entity == active) {
+        charger.charge_time -= super::CHARGE_THRESHOLD;
+    }
+
+    initiative.active = Some(active);
+    initiative.preview = Initiative::preview(&chargers, PREVIEW_LENGTH);
+
+    turn_number.0 += 1;
+    selection.0 = Some(SelectedUnit {
+        entity: active,
+        movement: None,
+        mode: Mode::Move,
+    });
+    turn_started.send(TurnStarted {
+        number: turn_number.0,
+    });
+
+    let phase_of_active = match faction {
+        Some(Faction::Enemy) => Phase::Enemy,
+        _ => Phase::Player,
+    };
+    if *phase.get() != phase_of_active {
+        next_phase.set(phase_of_active);
+    }
+
+    debug!(
+        "turn {}: {:?} acts, then {:?}",
+        turn_number.0, active, initiative.preview
+    );
+}
+
+pub fn reset_turns(
+    mut turn_number: ResMut<TurnNumber>,
+    mut initiative: ResMut<Initiative>,
+    mut next_phase: ResMut<NextState<Phase>>,
+) {
+    *turn_number = TurnNumber::default();
+    *initiative = Initiative::default();
+    next_phase.set(Phase::Player);
+}
+
+pub fn show_initiative(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 20.,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands.spawn((
+        InitiativeDisplay,
+        DespawnOnExit(GameState::Battle),
+        TextBundle::from_section("", style).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(24.),
+            right: Val::Px(24.),
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_initiative(
+    initiative: Res<Initiative>,
+    name_query: Query<&Name, With<Unit>>,
+    mut display_query: Query<&mut Text, With<InitiativeDisplay>>,
+) {
+    let name = |entity: Entity| {
+        name_query
+            .get(entity)
+            .map_or_else(|_| "?".to_string(), |name| name.to_string())
+    };
+
+    let mut lines = Vec::new();
+    if let Some(active) = initiative.active {
+        lines.push(format!("Acting: {}", name(active)));
+    }
+    lines.extend(
+        initiative
+            .preview
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| format!("{}. {}", index + 1, name(*entity))),
+    );
+
+    for mut text in &mut display_query {
+        text.sections[0].value = lines.join("\n");
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::Weapon;
+use crate::map::{Position, SpawnSide};
+use crate::turn::{Actions, ChargeTime};
+
+#[derive(Component, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub enum Faction {
+    #[default]
+    Player,
+    Enemy,
+}
+
+impl From<SpawnSide> for Faction {
+    fn from(side: SpawnSide) -> Self {
+        match side {
+            SpawnSide::Player => Faction::Player,
+            SpawnSide::Enemy => Faction::Enemy,
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone, Default)]
+pub struct Unit;
+
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Default, Debug)]
+#[reflect(Component)]
+pub struct Stats {
+    pub max_hp: i32,
+    #[serde(default)]
+    pub hp: i32,
+    pub move_range: i32,
+    /// Highest number of floors the unit can climb or drop in a single step.
+    pub jump: i32,
+    pub speed: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub evasion: i32,
+    /// How many tiles away the unit can see.
+    pub vision: i32,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+pub enum Stat {
+    MaxHp,
+    Move,
+    Jump,
+    Speed,
+    Attack,
+    Defense,
+    Evasion,
+    Vision,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Copy, Clone, Debug)]
+pub struct Modifier {
+    pub stat: Stat,
+    pub amount: i32,
+}
+
+/// Bonuses and penalties applied on top of a unit's base [`Stats`].
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+impl Stats {
+    /// These stats with every modifier applied.
+    pub fn with(&self, modifiers: &Modifiers) -> Stats {
+        modifiers.0.iter().fold(*self, |mut stats, modifier| {
+            let stat = match modifier.stat {
+                Stat::MaxHp => &mut stats.max_hp,
+                Stat::Move => &mut stats.move_range,
+                Stat::Jump => &mut stats.jump,
+                Stat::Speed => &mut stats.speed,
+                Stat::Attack => &mut stats.attack,
+                Stat::Defense => &mut stats.defense,
+                Stat::Evasion => &mut stats.evasion,
+                Stat::Vision => &mut stats.vision,
+            };
+            *stat += modifier.amount;
+            stats
+        })
+    }
+}
+
+/// Place of a unit among those spawned into its battle, kept so that ties between units
+/// don't depend on how entities happen to be allocated.
+#[derive(Component, Reflect, Serialize, Deserialize, Copy, Clone, Default, Debug)]
+#[reflect(Component)]
+pub struct SpawnOrder(pub u32);
+
+#[derive(Bundle, Default)]
+pub struct UnitBundle {
+    pub sprite: SpriteSheetBundle,
+    pub name: Name,
+    pub unit: Unit,
+    pub faction: Faction,
+    pub stats: Stats,
+    pub modifiers: Modifiers,
+    pub weapon: Weapon,
+    pub actions: Actions,
+    pub charge_time: ChargeTime,
+    pub spawn_order: SpawnOrder,
+    pub position: Position,
+}
@@ -0,0 +1,58 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::combat::Weapon;
+
+use super::{Modifier, Stats};
+
+/// A unit type as authored in `assets/units/*.unit.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct UnitKind {
+    pub name: String,
+    pub sprite: usize,
+    pub stats: Stats,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    #[serde(default)]
+    pub weapon: Weapon,
+}
+
+#[derive(Default)]
+pub struct UnitKindLoader;
+
+#[derive(Debug, Error)]
+pub enum UnitKindLoaderError {
+    #[error("could not read unit file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse unit file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for UnitKindLoader {
+    type Asset = UnitKind;
+    type Settings = ();
+    type Error = UnitKindLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut kind = ron::de::from_bytes::<UnitKind>(&bytes)?;
+            kind.stats.hp = kind.stats.max_hp;
+            Ok(kind)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["unit.ron"]
+    }
+}
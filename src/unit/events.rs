@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+
+use crate::map::Position;
+
+/// Sent every time a moving unit reaches the next waypoint of its path.
+#[derive(Event, Debug)]
+pub struct Stepped {
+    pub entity: Entity,
+    pub from: Position,
+    pub to: Position,
+}
@@ -0,0 +1,61 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::map::{Coordinates, Position};
+
+use super::{HOP_TIME, SPEED};
+
+/// Texture atlas shared by every unit sprite.
+#[derive(Resource)]
+pub struct UnitSprites(pub Handle<TextureAtlas>);
+
+/// Walks the waypoints of `path` in order, spending `step_time` seconds on each step.
+#[derive(Debug)]
+pub struct Movement {
+    pub path: VecDeque<Position>,
+    pub step_time: f32,
+    pub time_passed: f32,
+}
+
+impl Movement {
+    pub fn new(path: VecDeque<Position>) -> Self {
+        Self {
+            path,
+            step_time: SPEED / 1000.,
+            time_passed: 0.,
+        }
+    }
+
+    /// Steps that change floor take longer the bigger the height difference.
+    pub fn step_duration(&self, from: &Position, to: &Position) -> f32 {
+        let height = (to.floor.0 - from.floor.0).abs() as f32;
+        self.step_time * (1. + height * HOP_TIME)
+    }
+}
+
+/// What a click on the map does for the selected unit.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Mode {
+    #[default]
+    Move,
+    Attack,
+}
+
+#[derive(Debug)]
+pub struct SelectedUnit {
+    pub entity: Entity,
+    pub movement: Option<Movement>,
+    pub mode: Mode,
+}
+
+/// The unit the current faction is commanding, if any.
+#[derive(Resource, Default, Debug)]
+pub struct Selection(pub Option<SelectedUnit>);
+
+/// Tiles the selected unit can move to, computed from `origin`.
+#[derive(Resource, Default, Debug)]
+pub struct MovementRange {
+    pub origin: Option<(Entity, Coordinates, Mode)>,
+    pub tiles: HashSet<Coordinates>,
+}
@@ -0,0 +1,535 @@
+use std::collections::HashSet;
+
+use bevy::{
+    ecs::system::EntityCommands,
+    input::{mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+    sprite::Anchor,
+};
+
+use crate::action::Action;
+use crate::combat::Weapon;
+use crate::fog::{FogOfWar, Sight};
+use crate::map::{
+    Coordinates, CurrentMap, Floor, Highlight, Highlights, Map, MapData, Order, Position,
+    SelectCursor, Terrain, TileCancelled, TileConfirmed, SCALE_FACTOR,
+};
+use crate::run::{Encounter, Party, PartyMember};
+use crate::state::{DespawnOnExit, GameState};
+use crate::turn::Actions;
+
+use super::{
+    Faction, Mode, Modifiers, MovementRange, Selection, SpawnOrder, Stats, Stepped, Unit,
+    UnitBundle, UnitKind, UnitSprites, HOP_HEIGHT, PLAYER_FACTION, UNIT_ANCHOR, UNIT_SIZE,
+};
+
+pub fn load_sprites(
+    mut commands: Commands,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+) {
+    let columns = 4;
+    let rows = 33;
+    let texture_handle = asset_server.load("textures/IsometricTRPGAssetPack_OutlinedEntities.png");
+    let texture_atlas =
+        TextureAtlas::from_grid(texture_handle, UNIT_SIZE, columns, rows, None, None);
+
+    commands.insert_resource(UnitSprites(texture_atlases.add(texture_atlas)));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn setup(
+    mut commands: Commands,
+    maps: Res<Assets<MapData>>,
+    kinds: Res<Assets<UnitKind>>,
+    current_map: Res<CurrentMap>,
+    sprites: Res<UnitSprites>,
+    party: Res<Party>,
+    encounter: Res<Encounter>,
+    mut map: ResMut<Map>,
+) {
+    let Some(data) = maps.get(&current_map.0) else {
+        return;
+    };
+
+    let mut members = party.0.iter().enumerate();
+
+    for (index, spawn) in data.spawns.iter().enumerate() {
+        // Player spawns are filled by the party, in order, and left empty once it runs out.
+        let member = if Faction::from(spawn.side) == PLAYER_FACTION {
+            let Some(member) = members.next() else {
+                continue;
+            };
+            Some(member)
+        } else {
+            None
+        };
+
+        let handle = member.map_or(&spawn.handle, |(_, member)| &member.kind);
+        let Some(kind) = kinds.get(handle) else {
+            warn!("unit kind {} is not loaded", spawn.kind);
+            continue;
+        };
+
+        let mut stats = kind.stats;
+        let mut modifiers = kind.modifiers.clone();
+        match member {
+            Some((_, member)) => {
+                stats = member.stats.unwrap_or(stats);
+                modifiers.extend(member.modifiers.iter().copied());
+            }
+            None => modifiers.extend(encounter.enemy_modifiers.iter().copied()),
+        }
+
+        let faction = Faction::from(spawn.side);
+        let bundle = UnitBundle {
+            name: Name::new(kind.name.clone()),
+            faction,
+            stats,
+            modifiers: Modifiers(modifiers),
+            weapon: kind.weapon,
+            spawn_order: SpawnOrder(index as u32),
+            ..default()
+        };
+        let mut entity = spawn_unit(
+            &mut commands,
+            &mut map,
+            &sprites,
+            kind.sprite,
+            spawn.coordinates(),
+            faction == Faction::Enemy,
+            bundle,
+        );
+
+        if let Some((index, _)) = member {
+            entity.insert(PartyMember(index));
+        }
+    }
+}
+
+/// Spawns a unit showing `sprite` on top of the stack at `coordinates`, facing left if
+/// `flip_x`, and marks that tile as occupied.
+pub fn spawn_unit<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    map: &mut Map,
+    sprites: &UnitSprites,
+    sprite: usize,
+    coordinates: Coordinates,
+    flip_x: bool,
+    mut bundle: UnitBundle,
+) -> EntityCommands<'w, 's, 'a> {
+    bundle.position = Position {
+        coordinates,
+        floor: map.surface(coordinates).unwrap_or_default(),
+        order: Order(2.),
+    };
+
+    let mut sprite = TextureAtlasSprite::new(sprite);
+    sprite.anchor = Anchor::Custom(UNIT_ANCHOR);
+    sprite.flip_x = flip_x;
+
+    bundle.sprite = SpriteSheetBundle {
+        texture_atlas: sprites.0.clone(),
+        sprite,
+        transform: Transform {
+            translation: map.position_to_translation(&bundle.position),
+            scale: Vec3::splat(SCALE_FACTOR),
+            ..default()
+        },
+        ..default()
+    };
+
+    let entity = commands.spawn((bundle, DespawnOnExit(GameState::Battle)));
+    map.occupants.insert(coordinates, entity.id());
+
+    entity
+}
+
+pub fn reset_units(mut selection: ResMut<Selection>, mut range: ResMut<MovementRange>) {
+    *selection = Selection::default();
+    *range = MovementRange::default();
+}
+
+pub fn movement(
+    mut map: ResMut<Map>,
+    time: Res<Time>,
+    mut selection: ResMut<Selection>,
+    mut stepped: EventWriter<Stepped>,
+    mut unit_query: Query<(&mut Transform, &mut TextureAtlasSprite, &mut Position), With<Unit>>,
+) {
+    let Some(selected_unit) = &mut selection.0 else {
+        return;
+    };
+
+    let Ok((mut transform, mut sprite, mut unit_position)) =
+        unit_query.get_mut(selected_unit.entity)
+    else {
+        return;
+    };
+
+    let Some(movement) = &mut selected_unit.movement else {
+        return;
+    };
+
+    movement.time_passed += time.delta_seconds();
+
+    while let Some(next) = movement.path.front().copied() {
+        // Paths only avoid the units their faction knows of, so a hidden one ends the move.
+        if map
+            .occupants
+            .get(&next.coordinates)
+            .is_some_and(|occupant| *occupant != selected_unit.entity)
+        {
+            info!(
+                "{:?} ran into a hidden unit at {:?}",
+                selected_unit.entity, next.coordinates
+            );
+            movement.path.clear();
+            break;
+        }
+
+        let step_time = movement.step_duration(&unit_position, &next);
+
+        // Face the way the step goes on screen, keeping the facing on straight up or down steps.
+        let direction =
+            map.position_to_translation(&next).x - map.position_to_translation(&unit_position).x;
+        if direction != 0. {
+            sprite.flip_x = direction < 0.;
+        }
+
+        if movement.time_passed < step_time {
+            let percentage = movement.time_passed / step_time;
+            transform.translation = step_translation(&map, &unit_position, &next, percentage);
+            return;
+        }
+
+        movement.time_passed -= step_time;
+        movement.path.pop_front();
+
+        let from = *unit_position;
+        unit_position.coordinates = next.coordinates;
+        unit_position.floor = next.floor;
+
+        map.occupants.remove(&from.coordinates);
+        map.occupants
+            .insert(unit_position.coordinates, selected_unit.entity);
+
+        stepped.send(Stepped {
+            entity: selected_unit.entity,
+            from,
+            to: *unit_position,
+        });
+    }
+
+    transform.translation = map.position_to_translation(&unit_position);
+    selected_unit.movement = None;
+}
+
+/// Translation `percentage` of the way through a step, hopping in an arc when the floor changes.
+fn step_translation(map: &Map, from: &Position, to: &Position, percentage: f32) -> Vec3 {
+    let start_point = map.position_to_translation(from);
+    let end_point = map.position_to_translation(to);
+    let mut translation = start_point.lerp(end_point, percentage);
+
+    let height = (to.floor.0 - from.floor.0).abs();
+    if height == 0 {
+        return translation;
+    }
+
+    // Stay in front of whichever tile is taller so the unit doesn't clip into the block.
+    translation.z = start_point.z.max(end_point.z);
+
+    let arc = (start_point.y - end_point.y).abs() + map.tile_size.y * HOP_HEIGHT;
+    translation.y += arc * 4. * percentage * (1. - percentage);
+    translation
+}
+
+pub fn moving() -> impl Fn(Res<Selection>) -> bool {
+    move |selection: Res<Selection>| match &selection.0 {
+        Some(selected_unit) => selected_unit.movement.is_some(),
+        None => false,
+    }
+}
+
+type RangeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Faction,
+        &'static Position,
+        &'static Stats,
+        &'static Modifiers,
+        &'static Weapon,
+        &'static Actions,
+    ),
+    With<Unit>,
+>;
+
+pub fn update_range(
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    selection: Res<Selection>,
+    mut range: ResMut<MovementRange>,
+    mut highlights: ResMut<Highlights>,
+    unit_query: RangeQuery,
+) {
+    let origin = selection
+        .0
+        .as_ref()
+        .filter(|selected_unit| selected_unit.movement.is_none())
+        .and_then(|selected_unit| {
+            let (_, position, ..) = unit_query.get(selected_unit.entity).ok()?;
+            Some((
+                selected_unit.entity,
+                position.coordinates,
+                selected_unit.mode,
+            ))
+        });
+
+    if range.origin == origin {
+        return;
+    }
+
+    range.origin = origin;
+    range.tiles = HashSet::new();
+    highlights.0.clear();
+
+    let Some((entity, coordinates, mode)) = origin else {
+        return;
+    };
+
+    let (_, position, stats, modifiers, weapon, actions) = unit_query.get(entity).unwrap();
+    let stats = stats.with(modifiers);
+
+    match mode {
+        Mode::Move if !actions.can_move() => {}
+        Mode::Move => {
+            let occupants = fog.known_occupants(&map, PLAYER_FACTION, |entity| {
+                unit_query.get(entity).ok().map(|(faction, ..)| *faction)
+            });
+            let terrain = Terrain {
+                map: &map,
+                jump: Floor(stats.jump),
+                occupants: &occupants,
+            };
+            range.tiles = terrain.reachable(coordinates, stats.move_range);
+            highlights.0 = range
+                .tiles
+                .iter()
+                .map(|coordinates| (*coordinates, Highlight::Move))
+                .collect();
+        }
+        Mode::Attack => {
+            highlights.0 = weapon
+                .targets(&map, position)
+                .into_iter()
+                .map(|coordinates| (coordinates, Highlight::Attack))
+                .collect();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn click_to_move(
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    windows_query: Query<&Window>,
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    range: Res<MovementRange>,
+    selection: Res<Selection>,
+    mut mouse_button_input_events: EventReader<MouseButtonInput>,
+    mut orders: EventWriter<Action>,
+    unit_query: CommandQuery,
+) {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    let Some(cursor_position) = windows_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+    else {
+        return;
+    };
+
+    let Some(point) = camera.viewport_to_world_2d(camera_transform, cursor_position) else {
+        return;
+    };
+
+    for event in mouse_button_input_events.read() {
+        if let Some(selected_unit) = &selection.0 {
+            if selected_unit.mode == Mode::Attack {
+                continue;
+            }
+        }
+
+        if event.button == MouseButton::Left && event.state != ButtonState::Released {
+            let Some(tile) = map.pick(point) else {
+                continue;
+            };
+
+            let order = select(tile.coordinates, &fog, &selection, &unit_query).or_else(|| {
+                move_to(
+                    tile.coordinates,
+                    &map,
+                    &fog,
+                    &range,
+                    &selection,
+                    &unit_query,
+                )
+            });
+            if let Some(action) = order {
+                orders.send(action);
+            }
+        }
+    }
+}
+
+/// Does with the tile under a key or gamepad driven cursor what a click would.
+pub fn confirm_tile(
+    map: Res<Map>,
+    fog: Res<FogOfWar>,
+    range: Res<MovementRange>,
+    selection: Res<Selection>,
+    mut confirmed: EventReader<TileConfirmed>,
+    mut orders: EventWriter<Action>,
+    unit_query: CommandQuery,
+) {
+    for TileConfirmed(coordinates) in confirmed.read() {
+        if selection
+            .0
+            .as_ref()
+            .is_some_and(|selected_unit| selected_unit.mode == Mode::Attack)
+        {
+            continue;
+        }
+
+        let order = select(*coordinates, &fog, &selection, &unit_query)
+            .or_else(|| move_to(*coordinates, &map, &fog, &range, &selection, &unit_query));
+        if let Some(action) = order {
+            orders.send(action);
+        }
+    }
+}
+
+/// Backs out of attack mode, or else drops the selection.
+pub fn cancel_tile(
+    mut selection: ResMut<Selection>,
+    mut cancelled: EventReader<TileCancelled>,
+    mut orders: EventWriter<Action>,
+) {
+    if cancelled.read().count() == 0 {
+        return;
+    }
+
+    let Some(selected_unit) = &mut selection.0 else {
+        return;
+    };
+
+    match selected_unit.mode {
+        Mode::Attack => selected_unit.mode = Mode::Move,
+        Mode::Move => orders.send(Action::Select(None)),
+    }
+}
+
+type CommandQuery<'w, 's, 'a> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'a Position,
+        &'a Faction,
+        &'a Stats,
+        &'a Modifiers,
+        &'a Actions,
+    ),
+    With<Unit>,
+>;
+
+/// Selects the unit on `coordinates`, or deselects it if it already is. Units hidden by the
+/// fog can't be picked.
+fn select(
+    coordinates: Coordinates,
+    fog: &FogOfWar,
+    selection: &Selection,
+    unit_query: &CommandQuery,
+) -> Option<Action> {
+    let (entity, ..) = unit_query.iter().find(|(_, unit_position, faction, ..)| {
+        coordinates.eq(&unit_position.coordinates)
+            && (**faction == PLAYER_FACTION
+                || fog.sight(PLAYER_FACTION, coordinates) == Sight::Visible)
+    })?;
+
+    let selected = selection
+        .0
+        .as_ref()
+        .is_some_and(|selected_unit| selected_unit.entity == entity);
+
+    Some(Action::Select((!selected).then_some(coordinates)))
+}
+
+/// Walks the selected unit to `coordinates` if it's in range, around the units the player
+/// knows about.
+fn move_to(
+    coordinates: Coordinates,
+    map: &Map,
+    fog: &FogOfWar,
+    range: &MovementRange,
+    selection: &Selection,
+    unit_query: &CommandQuery,
+) -> Option<Action> {
+    let selected_unit = selection.0.as_ref()?;
+    let (_, unit_position, faction, stats, modifiers, actions) =
+        unit_query.get(selected_unit.entity).ok()?;
+    let jump = Floor(stats.with(modifiers).jump);
+
+    // Other factions can be inspected but not commanded.
+    if *faction != PLAYER_FACTION || !actions.can_move() || !range.tiles.contains(&coordinates) {
+        return None;
+    }
+
+    let occupants = fog.known_occupants(map, PLAYER_FACTION, |entity| {
+        unit_query
+            .get(entity)
+            .ok()
+            .map(|(_, _, faction, ..)| *faction)
+    });
+    let terrain = Terrain {
+        map,
+        jump,
+        occupants: &occupants,
+    };
+    let path = terrain.path(unit_position.coordinates, coordinates)?;
+    Some(Action::Move(path))
+}
+
+pub fn highlight_selected(
+    map: Res<Map>,
+    selection: Res<Selection>,
+    unit_query: Query<&Position, (With<Unit>, Without<SelectCursor>)>,
+    mut cursor_query: Query<(&mut Transform, &mut Visibility, &mut Position), With<SelectCursor>>,
+) {
+    let (mut tile_transform, mut visibility, mut position) = cursor_query.single_mut();
+
+    *visibility = Visibility::Hidden;
+
+    let Some(selected_unit) = &selection.0 else {
+        return;
+    };
+
+    if selected_unit.movement.is_some() {
+        return;
+    }
+
+    let Ok(unit_position) = unit_query.get(selected_unit.entity) else {
+        return;
+    };
+
+    position.coordinates = unit_position.coordinates;
+    position.floor = unit_position.floor;
+
+    tile_transform.translation = map.position_to_translation(&position);
+    tile_transform.translation.y -= 5.5;
+    *visibility = Visibility::Visible;
+}
@@ -17,15 +17,11 @@ impl Plugin for DisplayPlugin {
                 .set(window_plugin())
                 .set(ImagePlugin::default_nearest()),
         )
-        .add_systems(Startup, (setup, make_visible))
+        .add_systems(Startup, make_visible)
         .add_systems(Update, close_on_esc);
     }
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-}
-
 pub fn window_plugin() -> WindowPlugin {
     WindowPlugin {
         primary_window: Some(Window {